@@ -25,9 +25,20 @@
 //! arena.clear();
 //! assert_eq!(arena.get(id), None);
 //! ```
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
 pub use enums_arena_derive::*;
 
+/// Arena of the enum `E`, `Arena<Event, u32>` is `EventIdArena<u32, ()>`.
+pub type Arena<E, I, G = ()> = <E as enums_arena_defines::ArenaEnum>::Arena<I, G>;
+
+/// Id of [`Arena<E, I, G>`].
+pub type Id<E, I, G = ()> = <E as enums_arena_defines::ArenaEnum>::Id<I, G>;
+
 pub mod mock {
+    use alloc::vec::Vec;
 
     #[derive(PartialEq, Debug)]
     /// Example of a user-defined structure.
@@ -40,7 +51,7 @@ pub mod mock {
         Mock3((i8, u64, &'a str)),
     }
 
-    #[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+    #[derive(Clone, Copy, Debug, Eq, PartialEq, PartialOrd, Ord, Hash)]
     #[repr(u8)]
     /// Auto generated from [`Mock<T>`].
     pub enum MockExtendEnum {
@@ -52,6 +63,15 @@ pub mod mock {
     /// Auto generated
     pub type MockId<I, G> = (MockExtendEnum, I, G);
 
+    #[derive(Clone, Copy, Debug, Eq, PartialEq)]
+    /// Auto generated from [`Mock<T>`].
+    ///
+    /// Records the arena length at a point in time, see [`MockIdArena::checkpoint`].
+    pub struct MockCheckpoint<G> {
+        g: G,
+        len: usize,
+    }
+
     #[derive(Default)]
     /// Auto generated from [`Mock<T>`].
     ///
@@ -60,12 +80,24 @@ pub mod mock {
     /// Type parameter I is index data type, including [`u8, u16, u32, u64`].
     ///
     /// Type parameter G is arena generation data type, including [`u8, 16, u32, u64, ()`].
+    ///
+    /// Every id costs an `I` offset and a [`MockExtendEnum`] tag, every value with a payload
+    /// also costs an `I` back reference to its id. The tags find the kind of an index
+    /// without an id, which iterating in allocation order, `id_at`, `compact_id` and
+    /// snapshots need. The back references let `replace` and `rollback_to` fill the hole
+    /// left by a value with the last value of its variant. Slots kept by a rollback also
+    /// remember their generation, one `(usize, G)` run per rollback which kept slots of
+    /// another generation. `stats` of a generated arena counts all of them.
     pub struct MockIdArena<'a, T, I, G> {
         g: G,
         enums_vec_id_offset_of: Vec<I>,
+        enums_vec_id_kind_of: Vec<MockExtendEnum>,
+        enums_vec_id_g_of: enums_arena_defines::Generations<G>,
 
         mock2_vec: Vec<T>,
-        mock3_vec: Vec<(i8, u64, &'a str)>
+        mock2_vec_id_of: Vec<I>,
+        mock3_vec: Vec<(i8, u64, &'a str)>,
+        mock3_vec_id_of: Vec<I>,
     }
 
     /// Auto generated from [`Mock<T>`].
@@ -74,11 +106,89 @@ pub mod mock {
         I: enums_arena_defines::Index,
         G: enums_arena_defines::Generation,
     {
+        fn slot(&self, id: MockId<I, G>) -> Option<(MockExtendEnum, usize)> {
+            let (_, index, g) = id;
+            let index = index.to_usize();
+            if g != self.generation_of(index) {
+                return None;
+            }
+            let ty = *self.enums_vec_id_kind_of.get(index)?;
+            let real_index = self.enums_vec_id_offset_of.get(index)?.to_usize();
+            Some((ty, real_index))
+        }
+
+        fn generation_of(&self, index: usize) -> G {
+            self.enums_vec_id_g_of.get(index, self.g)
+        }
+
+        fn next_generation(&mut self) {
+            self.enums_vec_id_g_of.truncate(self.enums_vec_id_offset_of.len(), self.g);
+            self.g.add();
+        }
+
+        fn generation_before(&self, len: usize) -> G {
+            match len.checked_sub(1) {
+                Some(index) => self.generation_of(index),
+                None => self.g,
+            }
+        }
+
+        fn push_value(&mut self, index: I, val: Mock<'a, T>) -> (MockExtendEnum, I) {
+            match val {
+                Mock::Mock1 => (MockExtendEnum::Mock1, I::from_usize(0)),
+                Mock::Mock2(val) => {
+                    let real_index = I::from_usize(self.mock2_vec.len());
+                    self.mock2_vec.push(val);
+                    self.mock2_vec_id_of.push(index);
+                    (MockExtendEnum::Mock2, real_index)
+                }
+                Mock::Mock3(val) => {
+                    let real_index = I::from_usize(self.mock3_vec.len());
+                    self.mock3_vec.push(val);
+                    self.mock3_vec_id_of.push(index);
+                    (MockExtendEnum::Mock3, real_index)
+                }
+            }
+        }
+
+        fn take_value(&mut self, ty: MockExtendEnum, real_index: usize) -> Mock<'a, T> {
+            match ty {
+                MockExtendEnum::Mock1 => Mock::Mock1,
+                MockExtendEnum::Mock2 => {
+                    let val = self.mock2_vec.swap_remove(real_index);
+                    self.mock2_vec_id_of.swap_remove(real_index);
+                    if let Some(moved) = self.mock2_vec_id_of.get(real_index) {
+                        self.enums_vec_id_offset_of[moved.to_usize()] = I::from_usize(real_index);
+                    }
+                    Mock::Mock2(val)
+                }
+                MockExtendEnum::Mock3 => {
+                    let val = self.mock3_vec.swap_remove(real_index);
+                    self.mock3_vec_id_of.swap_remove(real_index);
+                    if let Some(moved) = self.mock3_vec_id_of.get(real_index) {
+                        self.enums_vec_id_offset_of[moved.to_usize()] = I::from_usize(real_index);
+                    }
+                    Mock::Mock3(val)
+                }
+            }
+        }
+
+        fn pop(&mut self) -> Option<Mock<'a, T>> {
+            let ty = self.enums_vec_id_kind_of.pop()?;
+            let real_index = self.enums_vec_id_offset_of.pop()?.to_usize();
+            Some(self.take_value(ty, real_index))
+        }
+
         /// Returns alloc times.
         pub fn len(&self) -> usize {
             self.enums_vec_id_offset_of.len()
         }
 
+        /// Returns `true` if nothing has been allocated.
+        pub fn is_empty(&self) -> bool {
+            self.enums_vec_id_offset_of.is_empty()
+        }
+
         /// Clears the arena, removing all values.
         ///
         /// Create a new generation and
@@ -86,13 +196,51 @@ pub mod mock {
         pub fn clear(&mut self) {
             self.g.add();
             self.enums_vec_id_offset_of.clear();
+            self.enums_vec_id_kind_of.clear();
+            self.enums_vec_id_g_of.clear();
             self.mock2_vec.clear();
+            self.mock2_vec_id_of.clear();
+            self.mock3_vec.clear();
+            self.mock3_vec_id_of.clear();
+        }
+
+        /// Remember the current length of the arena.
+        pub fn checkpoint(&self) -> MockCheckpoint<G> {
+            let len = self.enums_vec_id_offset_of.len();
+            MockCheckpoint {
+                g: self.generation_before(len),
+                len,
+            }
+        }
+
+        /// Drop every value allocated after the checkpoint.
+        ///
+        /// The arena moves to a new generation, ids allocated after the checkpoint
+        /// find nothing even after their slots are allocated again.
+        /// Returns `None` if the checkpoint comes from another generation
+        /// or the arena has already been rolled back past it.
+        pub fn rollback_to(&mut self, checkpoint: MockCheckpoint<G>) -> Option<()> {
+            let len = self.enums_vec_id_offset_of.len();
+            if checkpoint.len > len
+                || (checkpoint.len > 0 && checkpoint.g != self.generation_before(checkpoint.len))
+            {
+                return None;
+            }
+            if checkpoint.len == len {
+                return Some(());
+            }
+            while self.enums_vec_id_offset_of.len() > checkpoint.len {
+                self.pop();
+            }
+            self.next_generation();
+            Some(())
         }
 
         /// Auto generated from `Mock<T>::Mock1`.
         pub fn alloc_mock1(&mut self) -> MockId<I, G> {
             let index = I::from_usize(self.enums_vec_id_offset_of.len());
             self.enums_vec_id_offset_of.push(I::from_usize(0));
+            self.enums_vec_id_kind_of.push(MockExtendEnum::Mock1);
             (MockExtendEnum::Mock1, index, self.g)
         }
 
@@ -101,7 +249,9 @@ pub mod mock {
             let index = I::from_usize(self.enums_vec_id_offset_of.len());
             let real_index = I::from_usize(self.mock2_vec.len());
             self.mock2_vec.push(val);
+            self.mock2_vec_id_of.push(index);
             self.enums_vec_id_offset_of.push(real_index);
+            self.enums_vec_id_kind_of.push(MockExtendEnum::Mock2);
             (MockExtendEnum::Mock2, index, self.g)
         }
 
@@ -110,7 +260,9 @@ pub mod mock {
             let index = I::from_usize(self.enums_vec_id_offset_of.len());
             let real_index = I::from_usize(self.mock3_vec.len());
             self.mock3_vec.push(val);
+            self.mock3_vec_id_of.push(index);
             self.enums_vec_id_offset_of.push(real_index);
+            self.enums_vec_id_kind_of.push(MockExtendEnum::Mock3);
             (MockExtendEnum::Mock3, index, self.g)
         }
 
@@ -125,11 +277,7 @@ pub mod mock {
 
         /// Update value for then given id
         pub fn update(&mut self, id: MockId<I, G>, val: Mock<'a, T>) -> Option<()> {
-            let (ty, index, g) = id;
-            if g != self.g {
-                return None;
-            }
-            let real_index = self.enums_vec_id_offset_of[index.to_usize()].to_usize();
+            let (ty, real_index) = self.slot(id)?;
 
             match val {
                 Mock::Mock1 => {
@@ -141,71 +289,97 @@ pub mod mock {
                     if ty != MockExtendEnum::Mock2 {
                         return None;
                     }
-                    self.mock2_vec[real_index] = val;
+                    *self.mock2_vec.get_mut(real_index)? = val;
                 },
                 Mock::Mock3(val) => {
                     if ty != MockExtendEnum::Mock3 {
                         return None;
                     }
-                    self.mock3_vec[real_index] = val;
+                    *self.mock3_vec.get_mut(real_index)? = val;
                 },
             }
             Some(())
         }
 
+        /// Replace value for the given id and return the old value.
+        ///
+        /// The value may be another variant, the id stays valid
+        /// and [`id_at`](Self::id_at) returns it with the new kind.
+        pub fn replace(&mut self, id: MockId<I, G>, val: Mock<'a, T>) -> Option<Mock<'a, T>> {
+            let (ty, real_index) = self.slot(id)?;
+            let index = id.1;
+
+            match val {
+                Mock::Mock1 if ty == MockExtendEnum::Mock1 => Some(Mock::Mock1),
+                Mock::Mock2(val) if ty == MockExtendEnum::Mock2 => {
+                    let old = core::mem::replace(self.mock2_vec.get_mut(real_index)?, val);
+                    Some(Mock::Mock2(old))
+                }
+                Mock::Mock3(val) if ty == MockExtendEnum::Mock3 => {
+                    let old = core::mem::replace(self.mock3_vec.get_mut(real_index)?, val);
+                    Some(Mock::Mock3(old))
+                }
+                val => {
+                    let old = self.take_value(ty, real_index);
+                    let (ty, real_index) = self.push_value(index, val);
+                    self.enums_vec_id_kind_of[index.to_usize()] = ty;
+                    self.enums_vec_id_offset_of[index.to_usize()] = real_index;
+                    Some(old)
+                }
+            }
+        }
+
         /// Get enum type from the id
         pub fn ty(&self, id: MockId<I, G>) -> MockExtendEnum {
-            id.0
+            match self.slot(id) {
+                Some((ty, _)) => ty,
+                None => id.0,
+            }
+        }
+
+        /// Get enum type of the value the id points to
+        pub fn kind_of(&self, id: MockId<I, G>) -> Option<MockExtendEnum> {
+            Some(self.slot(id)?.0)
+        }
+
+        /// Get the current id of the index
+        pub fn id_at(&self, index: I) -> Option<MockId<I, G>> {
+            let ty = *self.enums_vec_id_kind_of.get(index.to_usize())?;
+            Some((ty, index, self.generation_of(index.to_usize())))
         }
 
         /// Auto generated from `Mock<T>::Mock2`.
         pub fn get_mock2(&self, id: MockId<I, G>) -> Option<&T> {
-            let (ty, index, g) = id;
-            if g != self.g {
-                return None;
-            }
-            let real_index = self.enums_vec_id_offset_of[index.to_usize()].to_usize();
+            let (ty, real_index) = self.slot(id)?;
             if let MockExtendEnum::Mock2 = ty {
-                return Some(&self.mock2_vec[real_index]);
+                return self.mock2_vec.get(real_index);
             }
             None
         }
 
         /// Auto generated from `Mock<T>::Mock3`.
         pub fn get_mock3(&self, id: MockId<I, G>) -> Option<&(i8, u64, &'a str)> {
-            let (ty, index, g) = id;
-            if g != self.g {
-                return None;
-            }
-            let real_index = self.enums_vec_id_offset_of[index.to_usize()].to_usize();
+            let (ty, real_index) = self.slot(id)?;
             if let MockExtendEnum::Mock3 = ty {
-                return Some(&self.mock3_vec[real_index]);
+                return self.mock3_vec.get(real_index);
             }
             None
         }
 
         /// Auto generated from `Mock<T>::Mock2`.
         pub fn get_mock2_mut(&mut self, id: MockId<I, G>) -> Option<&mut T> {
-            let (ty, index, g) = id;
-            if g != self.g {
-                return None;
-            }
-            let real_index = self.enums_vec_id_offset_of[index.to_usize()].to_usize();
+            let (ty, real_index) = self.slot(id)?;
             if let MockExtendEnum::Mock2 = ty {
-                return Some(&mut self.mock2_vec[real_index]);
+                return self.mock2_vec.get_mut(real_index);
             }
             None
         }
 
         /// Auto generated from `Mock<T>::Mock3`.
         pub fn get_mock3_mut(&mut self, id: MockId<I, G>) -> Option<&mut (i8, u64, &'a str)> {
-            let (ty, index, g) = id;
-            if g != self.g {
-                return None;
-            }
-            let real_index = self.enums_vec_id_offset_of[index.to_usize()].to_usize();
+            let (ty, real_index) = self.slot(id)?;
             if let MockExtendEnum::Mock3 = ty {
-                return Some(&mut self.mock3_vec[real_index]);
+                return self.mock3_vec.get_mut(real_index);
             }
             None
         }
@@ -218,25 +392,26 @@ pub mod mock {
         G: enums_arena_defines::Generation,
         T: Clone,
     {
-        pub fn get(&self, id: MockId<I, G>) -> Option<Mock<T>> {
-            let (ty, index, g) = id;
-            if g != self.g {
-                return None;
-            }
-            let ty_index = self.enums_vec_id_offset_of.get(index.to_usize())?.clone();
+        pub fn get(&self, id: MockId<I, G>) -> Option<Mock<'a, T>> {
+            let (ty, real_index) = self.slot(id)?;
             match ty {
-                MockExtendEnum::Mock1 => match ty_index.to_usize() {
+                MockExtendEnum::Mock1 => match real_index {
                     0 => Some(Mock::Mock1),
                     _ => None,
                 },
                 MockExtendEnum::Mock2 => Some(Mock::Mock2::<T>(
-                    self.mock2_vec.get(ty_index.to_usize()).cloned()?,
+                    self.mock2_vec.get(real_index).cloned()?,
                 )),
                 MockExtendEnum::Mock3 => Some(Mock::Mock3::<T>(
-                    self.mock3_vec.get(ty_index.to_usize()).cloned()?,
+                    self.mock3_vec.get(real_index).cloned()?,
                 ))
             }
         }
+
+        /// Get value by the index of the id
+        pub fn get_at(&self, index: I) -> Option<Mock<'a, T>> {
+            self.get(self.id_at(index)?)
+        }
     }
 }
 
@@ -321,4 +496,1253 @@ pub mod test {
         let id = arena.alloc_parent(("s", 1));
         assert_eq!(arena.get(id), Some(NodeV2::Parent(("s", 1))));
     }
+
+    #[test]
+    pub fn test_rollback() {
+        let mut arena = EnumIdArena::<u32, u8>::default();
+        let id = arena.alloc_value(1);
+        let checkpoint = arena.checkpoint();
+
+        let id2 = arena.alloc_detail(Detail { a: 1, b: 2 });
+        arena.alloc_none();
+        arena.alloc_value(2);
+        assert_eq!(arena.len(), 4);
+
+        assert_eq!(arena.rollback_to(checkpoint), Some(()));
+        assert_eq!(arena.len(), 1);
+        assert_eq!(arena.get(id), Some(Enum::Value(1)));
+        assert_eq!(arena.get(id2), None);
+        assert_eq!(arena.get_detail(id2), None);
+
+        let id3 = arena.alloc_value(3);
+        assert_eq!(arena.get(id3), Some(Enum::Value(3)));
+        assert_eq!(id3.1, id2.1);
+        assert_eq!(arena.get(id2), None);
+        assert!(!arena.contains(id2));
+        assert_eq!(arena.id_at(0), Some(id));
+
+        assert_eq!(arena.rollback_to(checkpoint), Some(()));
+        assert_eq!(arena.get(id3), None);
+        assert_eq!(arena.get(id), Some(Enum::Value(1)));
+        let inner = arena.checkpoint();
+        let id4 = arena.alloc_value(4);
+        assert_eq!(arena.rollback_to(inner), Some(()));
+        assert_eq!(arena.get(id4), None);
+        assert_eq!(arena.rollback_to(checkpoint), Some(()));
+
+        arena.alloc_value(5);
+        let stale = arena.checkpoint();
+        assert_eq!(arena.rollback_to(checkpoint), Some(()));
+        arena.alloc_value(6);
+        arena.alloc_value(7);
+        assert_eq!(arena.rollback_to(stale), None);
+
+        arena.clear();
+        assert_eq!(arena.rollback_to(checkpoint), None);
+    }
+
+    #[derive(EnumsIdArena, PartialEq, Eq, Debug)]
+    #[enums_arena(journal)]
+    enum Edit {
+        Value(i32),
+        None,
+        Detail(Detail),
+    }
+
+    #[test]
+    pub fn test_journal() {
+        let mut journal = EditJournal::<u32, ()>::default();
+        let id = journal.alloc_value(1);
+        let id2 = journal.alloc(Edit::Detail(Detail { a: 1, b: 2 }));
+        assert_eq!(journal.update(id, Edit::Value(2)), Some(()));
+        assert_eq!(journal.modify_detail(id2, |detail| detail.a = 3), Some(()));
+        assert_eq!(journal.modify_detail(id, |detail| detail.a = 4), None);
+
+        assert_eq!(journal.undo(), Some(()));
+        assert_eq!(journal.get(id2), Some(Edit::Detail(Detail { a: 1, b: 2 })));
+        assert_eq!(journal.undo(), Some(()));
+        assert_eq!(journal.get(id), Some(Edit::Value(1)));
+        assert_eq!(journal.undo(), Some(()));
+        assert_eq!(journal.len(), 1);
+        assert_eq!(journal.redo(), Some(()));
+        assert_eq!(journal.get(id2), Some(Edit::Detail(Detail { a: 1, b: 2 })));
+        assert_eq!(journal.redo(), Some(()));
+        assert_eq!(journal.redo(), Some(()));
+        assert_eq!(journal.redo(), None);
+        assert_eq!(journal.get(id2), Some(Edit::Detail(Detail { a: 3, b: 2 })));
+
+        journal.begin();
+        journal.alloc_none();
+        journal.update(id, Edit::Value(5));
+        journal.begin();
+        journal.alloc_value(6);
+        assert_eq!(journal.abort(), Some(()));
+        assert_eq!(journal.len(), 3);
+        assert_eq!(journal.commit(), Some(()));
+        assert_eq!(journal.commit(), None);
+
+        assert_eq!(journal.undo(), Some(()));
+        assert_eq!(journal.len(), 2);
+        assert_eq!(journal.get(id), Some(Edit::Value(2)));
+
+        assert_eq!(journal.replace(id, Edit::None), Some(Edit::Value(2)));
+        assert_eq!(journal.undo(), Some(()));
+        assert_eq!(journal.get(id), Some(Edit::Value(2)));
+    }
+
+    #[test]
+    pub fn test_journal_undo_alloc() {
+        let mut journal = EditJournal::<u32, u8>::default();
+        let a = journal.alloc_value(1);
+        assert_eq!(journal.undo(), Some(()));
+        let b = journal.alloc_detail(Detail { a: 1, b: 2 });
+        assert_eq!(journal.get(a), None);
+        assert_eq!(journal.get(b), Some(Edit::Detail(Detail { a: 1, b: 2 })));
+
+        // Redo allocates under the same id, so the changes recorded after it still apply.
+        journal.modify_detail(b, |detail| detail.a = 3);
+        for _ in 0..2 {
+            assert_eq!(journal.undo(), Some(()));
+            assert_eq!(journal.undo(), Some(()));
+            assert_eq!(journal.redo(), Some(()));
+            assert_eq!(journal.redo(), Some(()));
+        }
+        assert_eq!(journal.get(b), Some(Edit::Detail(Detail { a: 3, b: 2 })));
+
+        assert_eq!(journal.undo(), Some(()));
+        assert_eq!(journal.undo(), Some(()));
+        let c = journal.alloc_none();
+        assert_eq!(journal.get(b), None);
+        assert_eq!(journal.get(c), Some(Edit::None));
+        assert_eq!(journal.len(), 1);
+    }
+
+    #[test]
+    pub fn test_generation_wraps() {
+        let mut arena = EnumIdArena::<u32, u8>::default();
+        let id = arena.alloc_value(1);
+        for _ in 0..255 {
+            arena.clear();
+            arena.alloc_value(2);
+            assert_eq!(arena.get(id), None);
+        }
+        arena.clear();
+        arena.alloc_value(3);
+        assert_eq!(arena.get(id), Some(Enum::Value(3)));
+    }
+
+    #[test]
+    pub fn test_replace() {
+        let mut arena = EnumIdArena::<u32, ()>::default();
+        let id = arena.alloc_value(1);
+        let id2 = arena.alloc_value(2);
+        let id3 = arena.alloc_none();
+
+        assert_eq!(arena.replace(id, Enum::Value(3)), Some(Enum::Value(1)));
+        assert_eq!(arena.replace(id, Enum::ListAB((1, 2))), Some(Enum::Value(3)));
+        assert_eq!(arena.get(id), Some(Enum::ListAB((1, 2))));
+        assert_eq!(arena.get_value(id), None);
+        assert_eq!(arena.get_list_ab(id), Some(&(1, 2)));
+        assert_eq!(arena.ty(id), EnumExtendEnum::ListAB);
+        assert_eq!(arena.id_at(id.1), Some((EnumExtendEnum::ListAB, 0, ())));
+        assert_eq!(arena.get(id2), Some(Enum::Value(2)));
+
+        assert_eq!(arena.replace(id3, Enum::Value(4)), Some(Enum::None));
+        assert_eq!(arena.get(id3), Some(Enum::Value(4)));
+        assert_eq!(arena.replace(id2, Enum::None), Some(Enum::Value(2)));
+        assert_eq!(arena.get(id3), Some(Enum::Value(4)));
+
+        let checkpoint = arena.checkpoint();
+        let id4 = arena.alloc_value(5);
+        arena.replace(id3, Enum::None);
+        assert_eq!(arena.rollback_to(checkpoint), Some(()));
+        assert_eq!(arena.get(id4), None);
+        assert_eq!(arena.get(id3), Some(Enum::None));
+        assert_eq!(arena.get(id), Some(Enum::ListAB((1, 2))));
+    }
+
+    #[test]
+    pub fn test_alloc_many() {
+        let mut arena = EnumIdArena::<u32, ()>::default();
+        arena.alloc_none();
+        let range = arena.alloc_value_many(0..100);
+        assert_eq!(range.len(), 100);
+        assert_eq!(range.kind(), EnumExtendEnum::Value);
+        assert_eq!(arena.get(range.get(5).unwrap()), Some(Enum::Value(5)));
+        assert_eq!(range.get(100), None);
+        assert!(range
+            .iter()
+            .enumerate()
+            .all(|(n, id)| arena.get_value(id) == Some(&(n as i32))));
+
+        let range = arena.alloc_none_many(3);
+        assert_eq!(range.iter().count(), 3);
+        assert_eq!(arena.get(range.get(2).unwrap()), Some(Enum::None));
+
+        arena.extend([Enum::Value(1), Enum::None, Enum::ListAB((2, 3))]);
+        assert_eq!(arena.len(), 107);
+        assert_eq!(arena.get_at(106), Some(Enum::ListAB((2, 3))));
+    }
+
+    #[derive(EnumsIdArena, PartialEq, Eq, Debug)]
+    enum Wrapped<It> {
+        Item(It),
+        Nothing,
+    }
+
+    #[test]
+    pub fn test_extend_type_names() {
+        let mut arena = WrappedIdArena::<u8, u32, ()>::default();
+        arena.extend([Wrapped::Item(1), Wrapped::Nothing]);
+        assert_eq!(arena.get_at(0), Some(Wrapped::Item(1)));
+        let range = arena.alloc_item_many([2, 3]);
+        assert_eq!(arena.get_item(range.get(1).unwrap()), Some(&3));
+    }
+
+    #[test]
+    pub fn test_capacity() {
+        let mut arena = EnumIdArena::<u32, ()>::with_capacity(16);
+        assert!(arena.capacity() >= 16);
+        assert_eq!(arena.capacity_value(), 0);
+
+        arena.reserve_value(8);
+        assert!(arena.capacity_value() >= 8);
+        arena.reserve_for(&[(EnumExtendEnum::Detail, 4), (EnumExtendEnum::None, 32)]);
+        assert!(arena.capacity_detail() >= 4);
+        assert!(arena.capacity() >= 36);
+
+        arena.alloc_value(1);
+        arena.shrink_to_fit();
+        assert_eq!(arena.capacity_value(), 1);
+        assert_eq!(arena.capacity_detail(), 0);
+        assert_eq!(arena.capacity(), 1);
+    }
+
+    #[test]
+    pub fn test_stats() {
+        let mut arena = EnumIdArena::<u32, ()>::default();
+        arena.alloc_value_many([1, 2, 3]);
+        arena.alloc_none_many(2);
+        arena.shrink_to_fit();
+
+        let stats = arena.stats();
+        assert_eq!(stats.variants.len(), 4);
+        assert_eq!(stats.variants[0].name, "Value");
+        assert_eq!(stats.variants[0].count, 3);
+        assert_eq!(stats.variants[0].payload_size, 4);
+        assert_eq!(stats.variants[0].bytes_used, 3 * (4 + 4));
+        assert_eq!(stats.variants[1].count, 2);
+        assert_eq!(stats.variants[1].bytes_used, 0);
+        assert_eq!(stats.index_count, 5);
+        assert_eq!(stats.index_bytes_used, 5 * (4 + 1));
+        assert_eq!(stats.generation_count, 0);
+        assert_eq!(stats.bytes_used(), 24 + 25);
+        assert_eq!(stats.bytes_reserved(), 24 + 25);
+
+        let table = stats.to_string();
+        assert!(table.lines().next().unwrap().starts_with("variant"));
+        assert_eq!(table.lines().count(), 8);
+
+        let checkpoint = arena.checkpoint();
+        arena.alloc_none();
+        arena.rollback_to(checkpoint);
+        arena.shrink_to_fit();
+        let stats = arena.stats();
+        assert_eq!(stats.generation_count, 1);
+        assert_eq!(stats.generation_capacity, 1);
+        assert_eq!(stats.generation_bytes_used, size_of::<(usize, ())>());
+        assert_eq!(stats.bytes_reserved(), 24 + 25 + size_of::<(usize, ())>());
+    }
+
+    #[cfg(feature = "serde")]
+    #[derive(EnumsIdArena, PartialEq, Eq, Debug)]
+    #[enums_arena(serde)]
+    pub enum Saved<T> {
+        Value(T),
+        Pair((u8, String)),
+        Empty,
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    pub fn test_serde() {
+        let mut arena = SavedIdArena::<u32, u32, u16>::default();
+        arena.clear();
+        let id = arena.alloc_value(1);
+        let id2 = arena.alloc_pair((2, "pair".to_owned()));
+        let id3 = arena.alloc_empty();
+        let id4 = arena.alloc_value(4);
+        arena.replace(id, Saved::Pair((1, "replaced".to_owned())));
+        let checkpoint = arena.checkpoint();
+        let stale = arena.alloc_empty();
+        arena.rollback_to(checkpoint);
+        let id5 = arena.alloc_value(5);
+
+        let json = serde_json::to_string(&arena).unwrap();
+        let loaded: SavedIdArena<u32, u32, u16> = serde_json::from_str(&json).unwrap();
+        for id in [id, id2, id3, id4, id5] {
+            assert_eq!(loaded.get(id), arena.get(id));
+        }
+        assert_eq!(loaded.get(stale), None);
+        assert_eq!(loaded.get(id), Some(Saved::Pair((1, "replaced".to_owned()))));
+
+        let id_json = serde_json::to_string(&id2).unwrap();
+        let loaded_id: SavedId<u32, u16> = serde_json::from_str(&id_json).unwrap();
+        assert_eq!(loaded.get(loaded_id), Some(Saved::Pair((2, "pair".to_owned()))));
+
+        assert!(json.contains("\"offsets\":[1,0,0,0,1]"));
+        let broken = json.replace("\"offsets\":[1,0,0,0,1]", "\"offsets\":[0,0,0,0,1]");
+        assert!(serde_json::from_str::<SavedIdArena<u32, u32, u16>>(&broken).is_err());
+    }
+
+    /// Named like the serde items.
+    #[cfg(feature = "serde")]
+    #[derive(
+        PartialEq,
+        Eq,
+        Debug,
+        Clone,
+        enums_arena_defines::serde::Serialize,
+        enums_arena_defines::serde::Deserialize,
+    )]
+    #[serde(crate = "enums_arena_defines::serde")]
+    pub struct Error(String);
+
+    #[cfg(feature = "serde")]
+    #[derive(EnumsIdArena, PartialEq, Eq, Debug)]
+    #[enums_arena(serde)]
+    pub enum Reply<D> {
+        Failed(Error),
+        Done(D),
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    pub fn test_serde_names() {
+        let mut arena = ReplyIdArena::<u8, u32, ()>::default();
+        let id = arena.alloc_failed(Error("e".to_owned()));
+        let json = serde_json::to_string(&arena).unwrap();
+        let loaded: ReplyIdArena<u8, u32, ()> = serde_json::from_str(&json).unwrap();
+        assert_eq!(loaded.get(id), Some(Reply::Failed(Error("e".to_owned()))));
+    }
+
+    /// An older version of [`Record`].
+    #[cfg(feature = "serde")]
+    #[derive(EnumsIdArena, PartialEq, Eq, Debug)]
+    #[enums_arena(serde)]
+    pub enum RecordV1 {
+        Value(u32),
+        Note(String),
+        Gone(u8),
+    }
+
+    /// `Gone` is removed, `Flag` and `Added` are added.
+    #[cfg(feature = "serde")]
+    #[derive(EnumsIdArena, PartialEq, Eq, Debug)]
+    #[enums_arena(serde)]
+    pub enum Record {
+        Flag,
+        Value(u32),
+        Added(u64),
+        Note(String),
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    pub fn test_serde_schema() {
+        let mut arena = RecordV1IdArena::<u32, u16>::default();
+        let id = arena.alloc_value(1);
+        let note = arena.alloc_note("a".to_owned());
+        let json = serde_json::to_string(&arena).unwrap();
+
+        let mut loaded: RecordIdArena<u32, u16> = serde_json::from_str(&json).unwrap();
+        assert_eq!(
+            loaded.get((RecordExtendEnum::Value, id.1, id.2)),
+            Some(Record::Value(1))
+        );
+        assert_eq!(
+            loaded.get((RecordExtendEnum::Note, note.1, note.2)),
+            Some(Record::Note("a".to_owned()))
+        );
+        loaded.alloc_added(2);
+        assert_eq!(loaded.get_at(2), Some(Record::Added(2)));
+
+        arena.alloc_gone(7);
+        let after = arena.alloc_value(2);
+        let json = serde_json::to_string(&arena).unwrap();
+        let err = serde_json::from_str::<RecordIdArena<u32, u16>>(&json)
+            .err()
+            .unwrap();
+        assert!(err.to_string().contains("Gone"));
+
+        let mut removed = Vec::new();
+        let loaded = RecordIdArena::<u32, u16>::deserialize_with(
+            &mut serde_json::Deserializer::from_str(&json),
+            |value| {
+                removed.push((value.index, value.variant.to_owned()));
+            },
+        )
+        .unwrap();
+        assert_eq!(removed, vec![(2, "Gone".to_owned())]);
+        assert_eq!(loaded.len(), 3);
+        assert_eq!(
+            loaded.get((RecordExtendEnum::Value, id.1, id.2)),
+            Some(Record::Value(1))
+        );
+        assert_eq!(
+            loaded.get((RecordExtendEnum::Value, after.1, after.2)),
+            None
+        );
+        assert_eq!(loaded.get_at(2), Some(Record::Value(2)));
+    }
+
+    /// Values kept in chunks of two.
+    struct Chunks<T>(Vec<Vec<T>>);
+
+    impl<T> Default for Chunks<T> {
+        fn default() -> Self {
+            Self(Vec::new())
+        }
+    }
+
+    impl<T> enums_arena_defines::Storage for Chunks<T> {
+        type Item = T;
+
+        fn push(&mut self, val: T) {
+            match self.0.last_mut() {
+                Some(chunk) if chunk.len() < 2 => chunk.push(val),
+                _ => self.0.push(vec![val]),
+            }
+        }
+
+        fn pop(&mut self) -> Option<T> {
+            let val = self.0.last_mut()?.pop();
+            if self.0.last().is_some_and(|chunk| chunk.is_empty()) {
+                self.0.pop();
+            }
+            val
+        }
+
+        fn get(&self, index: usize) -> Option<&T> {
+            self.0.get(index / 2)?.get(index % 2)
+        }
+
+        fn get_mut(&mut self, index: usize) -> Option<&mut T> {
+            self.0.get_mut(index / 2)?.get_mut(index % 2)
+        }
+
+        fn len(&self) -> usize {
+            self.0.iter().map(|chunk| chunk.len()).sum()
+        }
+
+        fn clear(&mut self) {
+            self.0.clear();
+        }
+
+        fn capacity(&self) -> usize {
+            self.0.iter().map(|chunk| chunk.capacity().min(2)).sum()
+        }
+    }
+
+    #[derive(EnumsIdArena, PartialEq, Eq, Debug)]
+    #[enums_arena(storage = Chunks<_>)]
+    enum Chunked {
+        A(u32),
+        B(String),
+        C,
+    }
+
+    #[test]
+    pub fn test_storage() {
+        let mut arena = ChunkedIdArena::<u32, u16>::default();
+        arena.clear();
+        let ids: Vec<_> = (0..5).map(|i| arena.alloc_a(i)).collect();
+        let id = arena.alloc_b("b".to_string());
+        arena.alloc_c();
+        assert_eq!(arena.get(ids[3]), Some(Chunked::A(3)));
+        *arena.get_a_mut(ids[4]).unwrap() = 40;
+        assert_eq!(arena.get_a(ids[4]), Some(&40));
+        assert_eq!(arena.replace(ids[1], Chunked::B("c".to_string())), Some(Chunked::A(1)));
+        assert_eq!(arena.get(ids[1]), Some(Chunked::B("c".to_string())));
+        assert_eq!(arena.get(ids[4]), Some(Chunked::A(40)));
+        assert_eq!(arena.get_b(id), Some(&"b".to_string()));
+        assert_eq!(arena.capacity_a(), 4);
+
+        let checkpoint = arena.checkpoint();
+        arena.alloc_a(5);
+        arena.rollback_to(checkpoint);
+        assert_eq!(arena.len(), 7);
+        assert_eq!(arena.stats().variants[0].count, 4);
+    }
+
+    #[derive(EnumsIdArena, PartialEq, Eq, Debug)]
+    #[enums_arena(stable)]
+    enum Tree {
+        Leaf(String),
+        Value(u64),
+        Empty,
+    }
+
+    #[test]
+    pub fn test_stable_alloc_ref() {
+        let arena = TreeStableArena::<u32, u16>::default();
+        let (id, leaf) = arena.alloc_leaf_ref("leaf".to_string());
+        let (id2, value) = arena.alloc_value_ref(1);
+        let values: Vec<_> = (0..100).map(|i| arena.alloc_value_ref(i).1).collect();
+        let empty = arena.alloc(Tree::Empty);
+        assert_eq!(leaf, "leaf");
+        assert_eq!(*value, 1);
+        assert!(std::ptr::eq(arena.get_value(id2).unwrap(), value));
+        assert_eq!(values[99], &99);
+        assert_eq!(arena.get(id), Some(Tree::Leaf("leaf".to_string())));
+        assert_eq!(arena.kind_of(empty), Some(TreeExtendEnum::Empty));
+        assert_eq!(arena.len(), 103);
+    }
+
+    #[test]
+    pub fn test_stable_into_inner() {
+        let arena = TreeStableArena::<u32, u16>::default();
+        let (id, _) = arena.alloc_value_ref(1);
+
+        let mut arena = arena.into_inner();
+        let (id2, value) = arena.alloc_value_mut(5);
+        *value += 1;
+        assert_eq!(arena.get(id2), Some(Tree::Value(6)));
+        assert_eq!(arena.replace(id, Tree::Value(2)), Some(Tree::Value(1)));
+        assert_eq!(arena.get_value(id), Some(&2));
+    }
+
+    #[test]
+    pub fn test_stable_capacity() {
+        let mut arena = TreeIdArena::<u32, u16>::default();
+        let id = arena.alloc_value(1);
+        arena.alloc_value_many(0..100);
+        let checkpoint = arena.checkpoint();
+        arena.alloc_value_many(0..50);
+        arena.rollback_to(checkpoint);
+        assert_eq!(arena.get(id), Some(Tree::Value(1)));
+        assert_eq!(arena.capacity_value(), 248);
+        arena.shrink_to_fit();
+        assert_eq!(arena.capacity_value(), 120);
+        arena.clear();
+        arena.shrink_to_fit();
+        assert_eq!(arena.capacity_value(), 0);
+    }
+
+    #[derive(EnumsIdArena, PartialEq, Eq, Debug, Clone)]
+    #[enums_arena(ring)]
+    enum Log<'a> {
+        Message(&'a str),
+        Sample(u32),
+        Mark,
+    }
+
+    #[test]
+    pub fn test_ring() {
+        let mut ring = LogRingArena::<u16>::new(2);
+        let id = ring.alloc_message("a");
+        let id2 = ring.alloc_sample(1);
+        let id3 = ring.alloc(Log::Message("b"));
+        assert_eq!(ring.get(id), Some(Log::Message("a")));
+        let id4 = ring.alloc_message("c");
+        assert_eq!(ring.get(id), None);
+        assert_eq!(ring.get_message(id), None);
+        assert_eq!(ring.kind_of(id), None);
+        assert_eq!(ring.get(id2), Some(Log::Sample(1)));
+        assert_eq!(ring.len(), 3);
+        assert_eq!(ring.seq(), 4);
+
+        let messages: Vec<_> = ring.iter_message().collect();
+        assert_eq!(messages, vec![(id3, &"b"), (id4, &"c")]);
+        *ring.get_sample_mut(id2).unwrap() = 2;
+        assert_eq!(ring.get_sample(id2), Some(&2));
+
+        let mark = ring.alloc_mark();
+        ring.alloc_mark();
+        assert_eq!(ring.kind_of(mark), Some(LogExtendEnum::Mark));
+        ring.alloc_mark();
+        assert_eq!(ring.get(mark), None);
+
+        ring.clear();
+        assert!(ring.is_empty());
+        let id5 = ring.alloc_message("d");
+        assert_eq!(id5.1, id4.1);
+        assert_eq!(ring.get(id4), None);
+        assert_eq!(ring.get(id5), Some(Log::Message("d")));
+    }
+
+    #[test]
+    #[should_panic(expected = "must fit the index type")]
+    pub fn test_ring_capacity_fits_index() {
+        LogRingArena::<u8>::new(256).alloc_mark();
+        LogRingArena::<u8>::new(257);
+    }
+
+    #[test]
+    pub fn test_double_buffered() {
+        use enums_arena_defines::DoubleBuffered;
+
+        let mut frames = DoubleBuffered::<LogIdArena<u32, u8>>::new();
+        let id = frames.alloc(Log::Message("frame 0"));
+        assert_eq!(frames.get(id), Some(LogRef::Message(&"frame 0")));
+
+        frames.swap();
+        let id2 = frames.alloc(Log::Sample(1));
+        assert_eq!(frames.get(id), Some(LogRef::Message(&"frame 0")));
+        assert_eq!(frames.get(id2), Some(LogRef::Sample(&1)));
+        assert!(frames.previous().contains(id.1));
+        assert_eq!(frames.current().len(), 1);
+
+        frames.swap();
+        assert_eq!(frames.get(id), None);
+        assert_eq!(frames.get(id2), Some(LogRef::Sample(&1)));
+        let id3 = frames.alloc(Log::Mark);
+        assert_eq!(id3.1 .1, id.1 .1);
+        assert_eq!(frames.get(id), None);
+        assert_eq!(frames.get(id3), Some(LogRef::Mark));
+
+        for _ in 0..300 {
+            frames.swap();
+            assert!(frames.current().is_empty());
+            assert_eq!(frames.get(id), None);
+        }
+        assert_eq!(frames.frame(), 302);
+    }
+
+    #[test]
+    pub fn test_double_buffered_without_generation() {
+        use enums_arena_defines::DoubleBuffered;
+
+        // The frame in the id tells the values apart, the arenas can't.
+        let mut frames = DoubleBuffered::<LogIdArena<u32, ()>>::default();
+        let id = frames.alloc(Log::Sample(0));
+        frames.swap();
+        let id2 = frames.alloc(Log::Sample(1));
+        assert_eq!(id.1, id2.1);
+        assert_eq!(frames.get(id), Some(LogRef::Sample(&0)));
+        assert_eq!(frames.get(id2), Some(LogRef::Sample(&1)));
+
+        frames.swap();
+        let id3 = frames.alloc(Log::Sample(2));
+        assert_eq!(id.1, id3.1);
+        assert_eq!(frames.get(id), None);
+        assert!(!frames.contains(id));
+        assert_eq!(frames.get(id3), Some(LogRef::Sample(&2)));
+    }
+
+    #[derive(Default)]
+    struct LogPrinter(Vec<String>);
+
+    impl<'a> LogVisitor<'a, u32, u8> for LogPrinter {
+        fn visit_message(&mut self, _id: LogId<u32, u8>, val: &&'a str) {
+            self.0.push(val.to_string());
+        }
+
+        fn visit_mark(&mut self, id: LogId<u32, u8>) {
+            self.0.push(format!("mark {}", id.1));
+        }
+    }
+
+    #[test]
+    pub fn test_visitor() {
+        use enums_arena_defines::VisitOrder;
+
+        let mut arena = LogIdArena::<u32, u8>::default();
+        let id = arena.alloc_message("a");
+        arena.alloc_mark();
+        let id2 = arena.alloc_sample(1);
+        arena.alloc_message("b");
+
+        let mut printer = LogPrinter::default();
+        assert!(arena.visit(id, &mut printer));
+        assert!(arena.visit(id2, &mut printer));
+        assert_eq!(printer.0, vec!["a"]);
+
+        let mut printer = LogPrinter::default();
+        arena.visit_all(&mut printer, VisitOrder::Allocation);
+        assert_eq!(printer.0, vec!["a", "mark 1", "b"]);
+
+        arena.alloc_mark();
+        arena.replace(id, Log::Message("c"));
+        let mut printer = LogPrinter::default();
+        arena.visit_all(&mut printer, VisitOrder::VariantMajor);
+        assert_eq!(printer.0, vec!["c", "b", "mark 1", "mark 4"]);
+
+        arena.clear();
+        assert!(!arena.visit(id, &mut printer));
+    }
+
+    #[derive(EnumsIdArena, Clone)]
+    enum Bag<V> {
+        Leaf(V),
+        Empty,
+    }
+
+    struct LeafSum(u32);
+
+    impl BagVisitor<u32, u32, ()> for LeafSum {
+        fn visit_leaf(&mut self, _id: BagId<u32, ()>, val: &u32) {
+            self.0 += val;
+        }
+    }
+
+    #[test]
+    pub fn test_visitor_type_names() {
+        use enums_arena_defines::VisitOrder;
+
+        let mut arena = BagIdArena::<u32, u32, ()>::default();
+        let id = arena.alloc_leaf(1);
+        arena.alloc_empty();
+        arena.alloc_leaf(2);
+
+        let mut sum = LeafSum(0);
+        assert!(arena.visit(id, &mut sum));
+        arena.visit_all(&mut sum, VisitOrder::Allocation);
+        assert_eq!(sum.0, 4);
+    }
+
+    #[test]
+    pub fn test_dispatch_by_kind() {
+        let mut arena = LogIdArena::<u32, u8>::default();
+        arena.alloc_sample(1);
+        let id = arena.alloc_message("a");
+        arena.alloc_sample(2);
+        arena.alloc_mark();
+        arena.alloc_sample(3);
+
+        let mut sum = 0;
+        let mut marks = Vec::new();
+        let counts = arena.dispatch_by_kind(
+            &mut LogHandlers::new()
+                .on_sample(|_, val| sum += val)
+                .on_mark(|id| marks.push(id)),
+        );
+        assert_eq!(sum, 6);
+        assert_eq!(marks, vec![arena.id_at(3).unwrap()]);
+        assert_eq!(counts.sample, 3);
+        assert_eq!(counts.mark, 1);
+        assert_eq!(counts.message, 0);
+        assert_eq!(counts.total(), 4);
+
+        let mut ids = Vec::new();
+        let counts = arena.dispatch_by_kind(&mut LogHandlers::new().on_message(|id, _| ids.push(id)));
+        assert_eq!(ids, vec![id]);
+        assert_eq!(counts.total(), 1);
+    }
+
+    #[test]
+    pub fn test_map_into() {
+        let mut tree = TreeIdArena::<u32, u8>::default();
+        let leaf = tree.alloc_leaf("a".to_string());
+        let value = tree.alloc_value(2);
+        let empty = tree.alloc_empty();
+        assert_eq!(tree.get_ref(leaf), Some(TreeRef::Leaf(&"a".to_string())));
+        assert_eq!(
+            tree.iter().map(|(id, _)| id).collect::<Vec<_>>(),
+            vec![leaf, value, empty]
+        );
+
+        let (log, remap) = tree.map_into::<LogIdArena<u16, ()>>(|_, val| match val {
+            TreeRef::Leaf(s) => Log::Message(s),
+            TreeRef::Value(v) => Log::Sample(*v as u32),
+            TreeRef::Empty => Log::Mark,
+        });
+        assert_eq!(remap.len(), 3);
+        assert_eq!(log.get(remap.get(leaf).unwrap()), Some(Log::Message("a")));
+        assert_eq!(log.get(remap.get(value).unwrap()), Some(Log::Sample(2)));
+        assert_eq!(log.get(remap.get(empty).unwrap()), Some(Log::Mark));
+
+        tree.clear();
+        let leaf2 = tree.alloc_leaf("b".to_string());
+        assert_eq!(remap.get(leaf2), None);
+    }
+
+    #[test]
+    pub fn test_kind_helpers() {
+        assert_eq!(LogExtendEnum::COUNT, 3);
+        assert_eq!(
+            LogExtendEnum::ALL,
+            [LogExtendEnum::Message, LogExtendEnum::Sample, LogExtendEnum::Mark]
+        );
+        assert_eq!(LogExtendEnum::Sample.name(), "Sample");
+        assert_eq!("Mark".parse(), Ok(LogExtendEnum::Mark));
+        assert_eq!("mark".parse::<LogExtendEnum>(), Err(enums_arena_defines::UnknownKind));
+        assert_eq!(LogExtendEnum::try_from(1u8), Ok(LogExtendEnum::Sample));
+        assert_eq!(LogExtendEnum::try_from(3u16), Err(enums_arena_defines::UnknownKind));
+        assert!(LogExtendEnum::Message < LogExtendEnum::Mark);
+        for kind in LogExtendEnum::ALL {
+            assert_eq!(kind.name().parse(), Ok(kind));
+            assert_eq!(LogExtendEnum::try_from(kind as u8), Ok(kind));
+        }
+
+        let mut arena = LogIdArena::<u32, u8>::default();
+        let val = Log::Message("a");
+        let id = arena.alloc(val.clone());
+        assert_eq!(val.kind(), LogExtendEnum::Message);
+        assert_eq!(arena.kind_of(id), Some(val.kind()));
+        assert_eq!(Log::Mark.kind(), LogExtendEnum::Mark);
+    }
+
+    #[test]
+    pub fn test_kind_set() {
+        const TEXT: LogKindSet = LogKindSet::only(LogExtendEnum::Message).with(LogExtendEnum::Mark);
+        assert_eq!(TEXT.len(), 2);
+        assert!(TEXT.contains(LogExtendEnum::Mark));
+        assert!(!TEXT.contains(LogExtendEnum::Sample));
+        assert_eq!(!TEXT, LogKindSet::only(LogExtendEnum::Sample));
+        assert_eq!(TEXT | !TEXT, LogKindSet::ALL);
+        assert_eq!(TEXT & !TEXT, LogKindSet::EMPTY);
+        assert_eq!(LogKindSet::ALL - TEXT, !TEXT);
+        assert_eq!(
+            TEXT.iter().collect::<Vec<_>>(),
+            vec![LogExtendEnum::Message, LogExtendEnum::Mark]
+        );
+        assert_eq!(TEXT.iter().collect::<LogKindSet>(), TEXT);
+        assert_eq!(format!("{:?}", TEXT), "{Message, Mark}");
+
+        let mut set = LogKindSet::default();
+        assert!(set.insert(LogExtendEnum::Sample));
+        assert!(!set.insert(LogExtendEnum::Sample));
+        assert!(set.remove(LogExtendEnum::Sample));
+        assert!(set.is_empty());
+
+        let mut arena = LogIdArena::<u32, u8>::default();
+        let a = arena.alloc_message("a");
+        arena.alloc_sample(1);
+        let mark = arena.alloc_mark();
+        arena.alloc_sample(2);
+        assert_eq!(arena.iter_kinds(TEXT).collect::<Vec<_>>(), vec![a, mark]);
+        assert_eq!(arena.count_kinds(TEXT), 2);
+        assert_eq!(arena.count_kinds(!TEXT), 2);
+        assert_eq!(arena.count_kinds(LogKindSet::ALL), arena.len());
+        assert_eq!(arena.iter_kinds(LogKindSet::EMPTY).count(), 0);
+    }
+
+    #[derive(EnumsIdArena, PartialEq, Eq, Debug)]
+    enum Message {
+        #[arena(group = "input", convert)]
+        Key(char),
+        #[arena(group = "network")]
+        #[arena(convert)]
+        Packet(Vec<u8>),
+        #[arena(group = "input")]
+        Click,
+        #[arena(group = "timer")]
+        Tick(u64),
+        Quit,
+    }
+
+    #[test]
+    pub fn test_group() {
+        assert_eq!(
+            MessageGroup::ALL,
+            [MessageGroup::Input, MessageGroup::Network, MessageGroup::Timer]
+        );
+        assert_eq!(MessageGroup::Network.name(), "network");
+        assert_eq!(MessageExtendEnum::Click.group(), Some(MessageGroup::Input));
+        assert_eq!(MessageExtendEnum::Quit.group(), None);
+        assert_eq!(
+            MessageGroup::Input.kinds(),
+            MessageKindSet::only(MessageExtendEnum::Key).with(MessageExtendEnum::Click)
+        );
+
+        let mut arena = MessageIdArena::<u32, u8>::default();
+        let key = arena.alloc_key('a');
+        let tick = arena.alloc_tick(1);
+        arena.alloc_quit();
+        let click = arena.alloc_click();
+        assert_eq!(arena.iter_group_input().collect::<Vec<_>>(), vec![key, click]);
+        assert_eq!(arena.iter_group_timer().collect::<Vec<_>>(), vec![tick]);
+        assert_eq!(arena.iter_group(MessageGroup::Network).count(), 0);
+    }
+
+    /// Kinds of all values of any arena.
+    fn kinds<A: enums_arena_defines::EnumsArena>(arena: &A) -> Vec<A::Kind> {
+        arena.iter().filter_map(|(id, _)| arena.kind_of(id)).collect()
+    }
+
+    /// Allocate through the trait, then replace the value.
+    fn alloc_updated<A: enums_arena_defines::EnumsArena>(
+        arena: &mut A,
+        val: A::Enum,
+        new: A::Enum,
+    ) -> A::Id {
+        let id = arena.alloc(val);
+        arena.update(id, new).unwrap();
+        id
+    }
+
+    #[test]
+    pub fn test_enums_arena_trait() {
+        use enums_arena_defines::EnumsArena;
+
+        let mut log = LogIdArena::<u32, u8>::default();
+        log.alloc_sample(1);
+        let id = alloc_updated(&mut log, Log::Message("a"), Log::Message("b"));
+        assert_eq!(EnumsArena::get_ref(&log, id), Some(LogRef::Message(&"b")));
+        assert_eq!(kinds(&log), vec![LogExtendEnum::Sample, LogExtendEnum::Message]);
+
+        let mut message = MessageIdArena::<u16, ()>::default();
+        alloc_updated(&mut message, Message::Tick(1), Message::Tick(2));
+        message.alloc_quit();
+        assert_eq!(
+            kinds(&message),
+            vec![MessageExtendEnum::Tick, MessageExtendEnum::Quit]
+        );
+        assert_eq!(EnumsArena::len(&message), 2);
+
+        EnumsArena::clear(&mut log);
+        assert!(EnumsArena::is_empty(&log));
+        assert_eq!(EnumsArena::get_ref(&log, id), None);
+    }
+
+    /// Allocate a value into the arena of its enum.
+    fn alloc_into<E: enums_arena_defines::ArenaEnum>(
+        arena: &mut crate::Arena<E, u32>,
+        val: E,
+    ) -> crate::Id<E, u32> {
+        enums_arena_defines::EnumsArena::alloc(arena, val)
+    }
+
+    #[test]
+    pub fn test_arena_enum() {
+        let mut arena = crate::Arena::<Log, u32, u8>::default();
+        let id: crate::Id<Log, u32, u8> = arena.alloc_sample(1);
+        assert_eq!(arena.get(id), Some(Log::Sample(1)));
+
+        let mut arena: crate::Arena<Symbol<u8>, u32> = SymbolIdArena::default();
+        let id = alloc_into(&mut arena, Symbol::Resolved(1));
+        assert_eq!(arena.get_resolved(id), Some(&1));
+    }
+
+    #[derive(EnumsIdArena, PartialEq, Debug)]
+    #[enums_arena(typed)]
+    enum Widget<'a> {
+        Text(&'a str),
+        Size(u32),
+        Ratio(f32),
+        Hidden,
+    }
+
+    #[test]
+    pub fn test_typed() {
+        use enums_arena_defines::ArenaVariant;
+
+        let mut arena = WidgetIdArena::<u32, u8>::default();
+        let text = arena.alloc_typed("a");
+        let size = arena.alloc_typed(1u32);
+        arena.alloc_hidden();
+        let size2 = arena.alloc_typed(2u32);
+        assert_eq!(arena.get(text), Some(Widget::Text("a")));
+        assert_eq!(arena.get_as::<&str>(text), Some(&"a"));
+        assert_eq!(arena.get_as::<u32>(text), None);
+        *arena.get_as_mut::<u32>(size).unwrap() += 10;
+        assert_eq!(
+            arena.iter_of::<u32>().collect::<Vec<_>>(),
+            vec![(size, &11), (size2, &2)]
+        );
+        assert_eq!(arena.iter_of::<f32>().count(), 0);
+        assert_eq!(<f32 as ArenaVariant<Widget>>::KIND, WidgetExtendEnum::Ratio);
+    }
+
+    #[derive(EnumsIdArena, PartialEq, Debug)]
+    #[enums_arena(typed)]
+    enum Label<'get, 'iter> {
+        Name(&'get str),
+        Note(&'iter [u8]),
+    }
+
+    #[test]
+    pub fn test_typed_lifetime_names() {
+        let mut arena = LabelIdArena::<u32, u8>::default();
+        let name = arena.alloc_typed("a");
+        let note = arena.alloc_typed(&b"b"[..]);
+        assert_eq!(arena.get_as::<&str>(name), Some(&"a"));
+        assert_eq!(arena.get_as::<&[u8]>(note), Some(&&b"b"[..]));
+        assert_eq!(arena.iter_of::<&str>().count(), 1);
+    }
+
+    #[test]
+    pub fn test_convert() {
+        let key = Message::from('a');
+        assert_eq!(key, Message::Key('a'));
+        assert_eq!(key.as_key(), Some(&'a'));
+        assert_eq!(key.as_packet(), None);
+        assert_eq!(char::try_from(key), Ok('a'));
+        assert_eq!(Vec::<u8>::try_from(Message::Tick(1)), Err(Message::Tick(1)));
+
+        let packet: Message = vec![1, 2].into();
+        assert_eq!(packet.into_packet(), Some(vec![1, 2]));
+        assert_eq!(Message::Quit.into_key(), None);
+
+        let mut arena = MessageIdArena::<u32, u8>::default();
+        let id = arena.alloc('b'.into());
+        assert_eq!(arena.get_key(id), Some(&'b'));
+    }
+
+    /// Neither `Debug` nor `PartialEq`.
+    #[derive(Clone)]
+    struct Opaque(u8);
+
+    #[derive(EnumsIdArena)]
+    enum Plain {
+        A(Opaque),
+        B,
+    }
+
+    #[test]
+    pub fn test_plain_payload() {
+        let mut arena = PlainIdArena::<u32, ()>::default();
+        let a = arena.alloc_a(Opaque(1));
+        let b = arena.alloc_b();
+        assert!(matches!(arena.get_ref(a), Some(PlainRef::A(Opaque(1)))));
+        assert!(matches!(arena.get_ref(b), Some(PlainRef::B)));
+    }
+
+    #[derive(EnumsIdArena, PartialEq, Eq, Debug)]
+    enum Borrowed<'r, 'h> {
+        Text(&'r str),
+        Bytes(&'h [u8]),
+        Empty,
+    }
+
+    #[test]
+    pub fn test_borrowed_lifetime_names() {
+        let mut arena = BorrowedIdArena::<u32, ()>::default();
+        let id = arena.alloc_text("a");
+        assert_eq!(arena.get_ref(id), Some(BorrowedRef::Text(&"a")));
+        assert_eq!(format!("{:?}", arena.get_ref(id).unwrap()), "Text(\"a\")");
+        let (copy, remap) = arena.map_into::<BorrowedIdArena<u16, ()>>(|_, val| match val {
+            BorrowedRef::Text(s) => Borrowed::Text(s),
+            BorrowedRef::Bytes(b) => Borrowed::Bytes(b),
+            BorrowedRef::Empty => Borrowed::Empty,
+        });
+        assert_eq!(copy.get(remap.get(id).unwrap()), Some(Borrowed::Text("a")));
+
+        arena.alloc_bytes(b"b");
+        let mut bytes = 0;
+        let counts =
+            arena.dispatch_by_kind(&mut BorrowedHandlers::new().on_bytes(|_, b| bytes += b.len()));
+        assert_eq!((bytes, counts.bytes), (1, 1));
+    }
+
+    #[cfg(feature = "smallvec")]
+    #[derive(EnumsIdArena, PartialEq, Eq, Debug)]
+    #[enums_arena(storage = smallvec::SmallVec<[_; 4]>)]
+    enum Small {
+        A(u8),
+        B(u64),
+    }
+
+    #[cfg(feature = "smallvec")]
+    #[test]
+    pub fn test_storage_smallvec() {
+        let mut arena = SmallIdArena::<u16, ()>::default();
+        let id = arena.alloc_a(1);
+        let id2 = arena.alloc_b(2);
+        assert_eq!(arena.get(id), Some(Small::A(1)));
+        assert_eq!(arena.get_b(id2), Some(&2));
+        assert_eq!(arena.capacity_b(), 4);
+    }
+
+    #[derive(Clone, Copy, PartialEq, Debug)]
+    #[repr(C)]
+    struct Click {
+        x: i32,
+        y: i32,
+    }
+
+    unsafe impl enums_arena_defines::snapshot::Pod for Click {}
+
+    #[derive(EnumsIdArena, PartialEq, Debug)]
+    #[enums_arena(snapshot)]
+    enum Input {
+        Click(Click),
+        Tick(f64),
+        Key([u8; 3]),
+        Close,
+    }
+
+    #[test]
+    pub fn test_snapshot() {
+        use enums_arena_defines::snapshot::{AlignedBytes, SnapshotError};
+
+        let mut arena = InputIdArena::<u32, u16>::default();
+        arena.clear();
+        let id = arena.alloc_tick(1.5);
+        let id2 = arena.alloc_click(Click { x: 1, y: 2 });
+        let id3 = arena.alloc_close();
+        let id4 = arena.alloc_key(*b"abc");
+        arena.replace(id, Input::Key(*b"def"));
+        let checkpoint = arena.checkpoint();
+        let stale = arena.alloc_tick(2.5);
+        arena.rollback_to(checkpoint);
+        let id5 = arena.alloc_close();
+
+        let mut bytes = Vec::new();
+        arena.write_snapshot(&mut bytes).unwrap();
+        let bytes = AlignedBytes::new(&bytes);
+        let view = InputArenaView::<u32, u16>::from_bytes(&bytes).unwrap();
+        assert_eq!(view.len(), 5);
+        for id in [id, id2, id3, id4, id5] {
+            assert_eq!(view.get(id), arena.get(id));
+        }
+        assert_eq!(view.get(stale), None);
+        assert_eq!(view.get_click(id2), Some(&Click { x: 1, y: 2 }));
+        assert_eq!(view.get_tick(id), None);
+        assert_eq!(view.key_values(), &[*b"abc", *b"def"]);
+        assert!(view.tick_values().is_empty());
+
+        let loaded = view.to_arena();
+        for id in [id, id2, id3, id4, id5] {
+            assert_eq!(loaded.get(id), arena.get(id));
+        }
+        assert_eq!(loaded.get(stale), None);
+        arena.clear();
+        assert_eq!(view.get(arena.alloc_close()), None);
+
+        assert_eq!(
+            InputArenaView::<u32, u16>::from_bytes(&bytes[..bytes.len() - 1]).err(),
+            Some(SnapshotError::Truncated)
+        );
+        assert_eq!(
+            InputArenaView::<u64, u16>::from_bytes(&bytes).err(),
+            Some(SnapshotError::LayoutMismatch)
+        );
+        let mut corrupt = bytes.to_vec();
+        corrupt[0] = 0;
+        assert_eq!(
+            InputArenaView::<u32, u16>::from_bytes(&AlignedBytes::new(&corrupt)).err(),
+            Some(SnapshotError::BadMagic)
+        );
+    }
+
+    /// An older version of `Input`.
+    #[derive(EnumsIdArena, PartialEq, Debug)]
+    #[enums_arena(snapshot)]
+    enum InputV1 {
+        Tick(f64),
+        Gone(u32),
+        Close,
+        Click(Click),
+    }
+
+    #[derive(EnumsIdArena, PartialEq, Debug)]
+    #[enums_arena(snapshot)]
+    enum InputV2 {
+        Tick(f32),
+    }
+
+    /// Named like the snapshot error.
+    #[derive(Clone, Copy, PartialEq, Debug)]
+    #[repr(C)]
+    struct SnapshotError(u32);
+
+    unsafe impl enums_arena_defines::snapshot::Pod for SnapshotError {}
+
+    #[derive(EnumsIdArena, PartialEq, Debug)]
+    #[enums_arena(snapshot)]
+    enum Status {
+        Failed(SnapshotError),
+        Ok,
+    }
+
+    #[test]
+    pub fn test_snapshot_names() {
+        use enums_arena_defines::snapshot::AlignedBytes;
+
+        let mut arena = StatusIdArena::<u32, ()>::default();
+        let id = arena.alloc_failed(SnapshotError(3));
+        let mut bytes = Vec::new();
+        arena.write_snapshot(&mut bytes).unwrap();
+        let bytes = AlignedBytes::new(&bytes);
+        let view = StatusArenaView::<u32, ()>::from_bytes(&bytes).unwrap();
+        assert_eq!(view.get_failed(id), Some(&SnapshotError(3)));
+    }
+
+    #[test]
+    pub fn test_snapshot_schema() {
+        use enums_arena_defines::snapshot::{AlignedBytes, SnapshotError};
+
+        let mut arena = InputV1IdArena::<u32, u16>::default();
+        let id = arena.alloc_tick(1.5);
+        arena.alloc_close();
+        arena.alloc_click(Click { x: 3, y: 4 });
+        let mut bytes = Vec::new();
+        arena.write_snapshot(&mut bytes).unwrap();
+        let bytes = AlignedBytes::new(&bytes);
+
+        let view = InputArenaView::<u32, u16>::from_bytes(&bytes).unwrap();
+        let loaded = view.to_arena();
+        assert_eq!(loaded.get_at(0), Some(Input::Tick(1.5)));
+        assert_eq!(loaded.get_at(1), Some(Input::Close));
+        assert_eq!(loaded.get_at(2), Some(Input::Click(Click { x: 3, y: 4 })));
+        assert!(view.key_values().is_empty());
+
+        arena.alloc_gone(7);
+        let after = arena.alloc_close();
+        arena.alloc_tick(2.5);
+        let mut bytes = Vec::new();
+        arena.write_snapshot(&mut bytes).unwrap();
+        let bytes = AlignedBytes::new(&bytes);
+        assert_eq!(
+            InputArenaView::<u32, u16>::from_bytes(&bytes).err(),
+            Some(SnapshotError::UnknownVariant("Gone".to_string()))
+        );
+
+        let mut removed = Vec::new();
+        let view = InputArenaView::<u32, u16>::from_bytes_with(&bytes, |value| {
+            removed.push((value.index, value.variant, value.bytes.to_vec()));
+        })
+        .unwrap();
+        assert_eq!(removed, vec![(3, "Gone", 7u32.to_ne_bytes().to_vec())]);
+        assert_eq!(view.len(), 6);
+        let loaded = view.to_arena();
+        assert_eq!(loaded.len(), 5);
+        assert_eq!(loaded.get((InputExtendEnum::Tick, id.1, id.2)), Some(Input::Tick(1.5)));
+        assert_eq!(loaded.get((InputExtendEnum::Close, after.1, after.2)), None);
+        assert_eq!(loaded.get_at(3), Some(Input::Close));
+        assert_eq!(loaded.get_at(4), Some(Input::Tick(2.5)));
+
+        assert_eq!(
+            InputV2ArenaView::<u32, u16>::from_bytes(&bytes).err(),
+            Some(SnapshotError::TypeMismatch {
+                variant: "Tick".to_string(),
+                stored: "f64".to_string(),
+                expected: "f32".to_string(),
+            })
+        );
+    }
+
+    #[derive(EnumsIdArena, PartialEq, Eq, Debug)]
+    #[enums_arena(compact_id, journal)]
+    pub enum Symbol<T> {
+        Unresolved(&'static str),
+        Resolved(T),
+    }
+
+    #[test]
+    pub fn test_compact_id() {
+        let mut arena = SymbolIdArena::<u64, u32, u8>::default();
+        let id = arena.alloc_unresolved("a");
+        assert_eq!(id, (0, 0));
+        assert_eq!(arena.kind_of(id), Some(SymbolExtendEnum::Unresolved));
+
+        arena.replace(id, Symbol::Resolved(1));
+        assert_eq!(arena.kind_of(id), Some(SymbolExtendEnum::Resolved));
+        assert_eq!(arena.get_resolved(id), Some(&1));
+        assert_eq!(arena.get_at(0), Some(Symbol::Resolved(1)));
+        assert_eq!(arena.get_at(1), None);
+
+        arena.clear();
+        assert_eq!(arena.kind_of(id), None);
+
+        let mut journal = SymbolJournal::<u64, u32, u8>::default();
+        let id = journal.alloc_resolved(2);
+        journal.undo();
+        assert_eq!(journal.get(id), None);
+        let other = journal.alloc_unresolved("b");
+        assert_eq!(journal.get(id), None);
+        assert_eq!(journal.get(other), Some(Symbol::Unresolved("b")));
+    }
 }
@@ -0,0 +1,72 @@
+use crate::{Generation, Index};
+
+/// Operations of every generated `{Name}IdArena`, for code which accepts any arena.
+pub trait EnumsArena {
+    /// The user enum.
+    type Enum;
+    type Id: Copy;
+    /// `{Name}ExtendEnum`.
+    type Kind: Copy;
+    /// `{Name}Ref`, a value borrowed in place.
+    type Ref<'a>
+    where
+        Self: 'a;
+
+    fn alloc(&mut self, val: Self::Enum) -> Self::Id;
+
+    fn get_ref(&self, id: Self::Id) -> Option<Self::Ref<'_>>;
+
+    /// Returns `None` if `id` is not in the arena or `val` is of another kind.
+    fn update(&mut self, id: Self::Id, val: Self::Enum) -> Option<()>;
+
+    fn kind_of(&self, id: Self::Id) -> Option<Self::Kind>;
+
+    fn len(&self) -> usize;
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Remove all values and bump the generation.
+    fn clear(&mut self);
+
+    /// Values in allocation order.
+    fn iter(&self) -> impl Iterator<Item = (Self::Id, Self::Ref<'_>)>;
+}
+
+/// Implemented by the user enum, names the generated types by the enum.
+pub trait ArenaEnum: Sized {
+    /// `{Name}IdArena`.
+    type Arena<I: Index, G: Generation>: EnumsArena<
+        Enum = Self,
+        Id = Self::Id<I, G>,
+        Kind = Self::Kind,
+    >;
+    /// `{Name}Id`.
+    type Id<I: Index, G: Generation>: Copy;
+    /// `{Name}ExtendEnum`.
+    type Kind: Copy;
+}
+
+/// Implemented by the payload type of each variant with `#[enums_arena(typed)]`,
+/// every variant needs a distinct payload type.
+pub trait ArenaVariant<E: ArenaEnum>: Sized {
+    /// Kind of the variant holding the payload.
+    const KIND: E::Kind;
+
+    fn alloc<I: Index, G: Generation>(arena: &mut E::Arena<I, G>, val: Self) -> E::Id<I, G>;
+
+    fn get<I: Index, G: Generation>(arena: &E::Arena<I, G>, id: E::Id<I, G>) -> Option<&Self>;
+
+    fn get_mut<I: Index, G: Generation>(
+        arena: &mut E::Arena<I, G>,
+        id: E::Id<I, G>,
+    ) -> Option<&mut Self>;
+
+    /// Payloads of the variant in storage order.
+    fn iter<'a, I: Index, G: Generation>(
+        arena: &'a E::Arena<I, G>,
+    ) -> impl Iterator<Item = (E::Id<I, G>, &'a Self)>
+    where
+        Self: 'a;
+}
@@ -0,0 +1,112 @@
+use alloc::vec::Vec;
+
+use crate::{StableStorage, Storage};
+
+const FIRST_CHUNK: usize = 8;
+
+/// Storage in chunks which are never reallocated, so values don't move when more are pushed.
+///
+/// The `k`th chunk holds `8 << k` values.
+pub struct ChunkedVec<T> {
+    chunks: Vec<Vec<T>>,
+    len: usize,
+}
+
+impl<T> Default for ChunkedVec<T> {
+    fn default() -> Self {
+        Self {
+            chunks: Vec::new(),
+            len: 0,
+        }
+    }
+}
+
+/// Chunk of the value at `index` and its position in the chunk.
+fn chunk_of(index: usize) -> (usize, usize) {
+    let k = (index / FIRST_CHUNK + 1).ilog2() as usize;
+    (k, index - FIRST_CHUNK * ((1 << k) - 1))
+}
+
+impl<T> ChunkedVec<T> {
+    /// Push a chunk without pushing a value into it.
+    fn grow(&mut self) {
+        let k = self.chunks.len();
+        self.chunks.push(Vec::with_capacity(FIRST_CHUNK << k));
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &T> {
+        self.chunks.iter().flatten()
+    }
+}
+
+impl<T> Storage for ChunkedVec<T> {
+    type Item = T;
+
+    fn push(&mut self, val: T) {
+        self.push_ptr(val);
+    }
+
+    fn pop(&mut self) -> Option<T> {
+        let (k, _) = chunk_of(self.len.checked_sub(1)?);
+        self.len -= 1;
+        self.chunks[k].pop()
+    }
+
+    fn get(&self, index: usize) -> Option<&T> {
+        let (k, i) = chunk_of(index);
+        self.chunks.get(k)?.get(i)
+    }
+
+    fn get_mut(&mut self, index: usize) -> Option<&mut T> {
+        let (k, i) = chunk_of(index);
+        self.chunks.get_mut(k)?.get_mut(i)
+    }
+
+    fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Values are dropped, the chunks are kept.
+    fn clear(&mut self) {
+        for chunk in &mut self.chunks {
+            chunk.clear();
+        }
+        self.len = 0;
+    }
+
+    fn capacity(&self) -> usize {
+        FIRST_CHUNK * ((1 << self.chunks.len()) - 1)
+    }
+
+    fn reserve(&mut self, additional: usize) {
+        while self.capacity() < self.len + additional {
+            self.grow();
+        }
+    }
+
+    /// Free the empty chunks.
+    fn shrink_to_fit(&mut self) {
+        let used = match self.len {
+            0 => 0,
+            len => chunk_of(len - 1).0 + 1,
+        };
+        self.chunks.truncate(used);
+        self.chunks.shrink_to_fit();
+    }
+}
+
+unsafe impl<T> StableStorage for ChunkedVec<T> {
+    fn push_ptr(&mut self, val: T) -> *mut T {
+        let (k, _) = chunk_of(self.len);
+        while self.chunks.len() <= k {
+            self.grow();
+        }
+        let chunk = &mut self.chunks[k];
+        // The chunk is allocated with its final capacity, pushing never reallocates it.
+        debug_assert!(chunk.len() < chunk.capacity());
+        chunk.push(val);
+        self.len += 1;
+        // SAFETY: the value was just pushed, the pointer is in bounds.
+        unsafe { chunk.as_mut_ptr().add(chunk.len() - 1) }
+    }
+}
@@ -0,0 +1,85 @@
+use crate::EnumsArena;
+
+/// Id of a value in a [`DoubleBuffered`], the id in its arena tagged with the frame
+/// it was allocated in.
+pub type FrameId<Id> = (u64, Id);
+
+/// A pair of arenas, values are written to the current one
+/// while the values of the previous frame are still readable.
+///
+/// Ids carry their frame, which picks the arena, so the generations of the two
+/// arenas only tell apart the values of one arena.
+pub struct DoubleBuffered<A> {
+    /// The arena of frame `frame` is `arenas[frame % 2]`.
+    arenas: [A; 2],
+    frame: u64,
+}
+
+impl<A: EnumsArena + Default> Default for DoubleBuffered<A> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<A: EnumsArena> DoubleBuffered<A> {
+    pub fn new() -> Self
+    where
+        A: Default,
+    {
+        Self {
+            arenas: [A::default(), A::default()],
+            frame: 0,
+        }
+    }
+
+    /// Number of frames started by [`swap`](Self::swap).
+    pub fn frame(&self) -> u64 {
+        self.frame
+    }
+
+    fn index(frame: u64) -> usize {
+        (frame % 2) as usize
+    }
+
+    /// The arena of this frame.
+    pub fn current(&self) -> &A {
+        &self.arenas[Self::index(self.frame)]
+    }
+
+    /// The arena of the previous frame.
+    pub fn previous(&self) -> &A {
+        &self.arenas[Self::index(self.frame + 1)]
+    }
+
+    /// Start the next frame, the current arena becomes the previous one
+    /// and the values of the previous frame are cleared.
+    pub fn swap(&mut self) {
+        self.frame += 1;
+        self.arenas[Self::index(self.frame)].clear();
+    }
+
+    /// Allocate the value in the arena of this frame.
+    pub fn alloc(&mut self, val: A::Enum) -> FrameId<A::Id> {
+        let id = self.arenas[Self::index(self.frame)].alloc(val);
+        (self.frame, id)
+    }
+
+    /// The arena which holds the value of `id`, if it is of this or the previous frame.
+    pub fn arena_of(&self, id: FrameId<A::Id>) -> Option<&A> {
+        let (frame, id) = id;
+        if frame > self.frame || frame + 1 < self.frame {
+            return None;
+        }
+        let arena = &self.arenas[Self::index(frame)];
+        arena.kind_of(id).map(|_| arena)
+    }
+
+    pub fn contains(&self, id: FrameId<A::Id>) -> bool {
+        self.arena_of(id).is_some()
+    }
+
+    /// Finds values of this and the previous frame.
+    pub fn get(&self, id: FrameId<A::Id>) -> Option<A::Ref<'_>> {
+        self.arena_of(id)?.get_ref(id.1)
+    }
+}
@@ -0,0 +1,92 @@
+use alloc::vec::Vec;
+
+use crate::Generation;
+
+/// Generations of the slots of an arena kept by a rollback.
+///
+/// A rollback bumps the generation of the arena, the slots it keeps stay in the
+/// generation they were allocated in, so ids of the dropped slots don't find the
+/// values allocated after the rollback. Slots allocated since the last rollback
+/// have the current generation of the arena and take no space here.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Generations<G> {
+    /// `(end, g)`, the slots from the previous `end` up to this one have `g`.
+    runs: Vec<(usize, G)>,
+}
+
+impl<G> Default for Generations<G> {
+    fn default() -> Self {
+        Self { runs: Vec::new() }
+    }
+}
+
+impl<G: Generation> Generations<G> {
+    /// Check runs read back from a serialized arena, their ends must be increasing.
+    pub fn from_runs(runs: Vec<(usize, G)>) -> Option<Self> {
+        let mut start = 0;
+        for (end, _) in &runs {
+            if *end <= start {
+                return None;
+            }
+            start = *end;
+        }
+        Some(Self { runs })
+    }
+
+    pub fn runs(&self) -> &[(usize, G)] {
+        &self.runs
+    }
+
+    /// Number of runs which fit without reallocating.
+    pub fn capacity(&self) -> usize {
+        self.runs.capacity()
+    }
+
+    pub fn shrink_to_fit(&mut self) {
+        self.runs.shrink_to_fit();
+    }
+
+    /// Generation of the slot `index`, `current` if it is allocated after the last rollback.
+    pub fn get(&self, index: usize, current: G) -> G {
+        if self.runs.is_empty() {
+            return current;
+        }
+        let run = self.runs.partition_point(|(end, _)| *end <= index);
+        self.runs.get(run).map_or(current, |(_, g)| *g)
+    }
+
+    /// Keep the generations of the first `len` slots before the arena in generation
+    /// `current` is rolled back to `len` slots and bumps its generation.
+    pub fn truncate(&mut self, len: usize, current: G) {
+        let start = self.runs.last().map_or(0, |(end, _)| *end);
+        if start < len {
+            match self.runs.last_mut() {
+                Some(last) if last.1 == current => last.0 = len,
+                _ => self.runs.push((len, current)),
+            }
+            return;
+        }
+        let kept = self.runs.partition_point(|(end, _)| *end < len) + usize::from(len > 0);
+        self.runs.truncate(kept);
+        if let Some(last) = self.runs.last_mut() {
+            last.0 = len;
+        }
+    }
+
+    /// Give the slot `index`, the next one to be allocated, the generation `g` it had
+    /// before it was dropped, so its old id finds it again.
+    pub fn reuse(&mut self, index: usize, g: G, current: G) {
+        self.truncate(index, current);
+        if g == current {
+            return;
+        }
+        match self.runs.last_mut() {
+            Some(last) if last.1 == g => last.0 = index + 1,
+            _ => self.runs.push((index + 1, g)),
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.runs.clear();
+    }
+}
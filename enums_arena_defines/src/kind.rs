@@ -0,0 +1,22 @@
+use core::fmt;
+
+/// Returned when a name or a number is not a variant of `{Name}ExtendEnum`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct UnknownKind;
+
+impl fmt::Display for UnknownKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "unknown variant kind")
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for UnknownKind {}
+
+/// A value of a variant the enum doesn't have any more, found while loading a serialized arena.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RemovedKind<'a> {
+    /// Index of the value in the serialized arena.
+    pub index: usize,
+    pub variant: &'a str,
+}
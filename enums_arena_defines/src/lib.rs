@@ -1,4 +1,49 @@
-use std::{fmt::Debug, hash::Hash};
+#![cfg_attr(not(feature = "std"), no_std)]
+
+use core::{fmt::Debug, hash::Hash};
+
+#[doc(hidden)]
+pub extern crate alloc;
+
+mod arena;
+mod chunked;
+mod frame;
+mod generations;
+mod kind;
+pub mod snapshot;
+mod stats;
+mod storage;
+
+pub use arena::{ArenaEnum, ArenaVariant, EnumsArena};
+pub use chunked::ChunkedVec;
+pub use frame::{DoubleBuffered, FrameId};
+pub use generations::Generations;
+pub use kind::{RemovedKind, UnknownKind};
+pub use stats::{ArenaStats, VariantStats};
+pub use storage::{StableStorage, Storage};
+
+#[cfg(feature = "serde")]
+#[doc(hidden)]
+pub use serde;
+
+/// Order of visiting all values of an arena.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum VisitOrder {
+    /// The order the values are allocated in.
+    #[default]
+    Allocation,
+    /// All values of the first variant, then all of the second and so on,
+    /// each variant in storage order.
+    VariantMajor,
+}
+
+/// An arena values can be allocated into, implemented by every generated arena.
+pub trait AllocArena {
+    type Id: Copy;
+    type Value;
+
+    fn alloc(&mut self, val: Self::Value) -> Self::Id;
+}
 
 pub trait Index: PartialEq + Eq + Copy + Debug + Hash {
     fn to_usize(self) -> usize;
@@ -6,6 +51,10 @@ pub trait Index: PartialEq + Eq + Copy + Debug + Hash {
 }
 
 pub trait Generation: PartialEq + Eq + Copy + Debug + Hash {
+    /// Bump the generation, a number wraps around instead of overflowing.
+    ///
+    /// An arena can be cleared any number of times, but an id kept for a whole
+    /// cycle of its generation type finds a value again.
     fn add(&mut self);
 }
 
@@ -26,7 +75,7 @@ macro_rules! define_generation_number {
     ($ty: ty) => {
         impl Generation for $ty {
             fn add(&mut self) {
-                *self += 1;
+                *self = self.wrapping_add(1);
             }
         }
     };
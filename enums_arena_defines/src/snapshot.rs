@@ -0,0 +1,460 @@
+//! Binary snapshot of an arena which can be read in place.
+//!
+//! Layout, all integers are native endian:
+//!
+//! | bytes            | content                                            |
+//! |------------------|----------------------------------------------------|
+//! | 8                | magic `ENUMSARN`                                   |
+//! | 4                | format version                                     |
+//! | 4                | endian mark `0x01020304`                           |
+//! | 4                | number of sections                                 |
+//! | 4                | reserved                                           |
+//! | 24 * sections    | section table, `(offset, item count, item size)`   |
+//! | ...              | sections, each aligned to [`SECTION_ALIGN`]        |
+//!
+//! The sections of an arena are the generations, the ends of the generation runs
+//! (see [`Generations`](crate::Generations)), the kind tags, the offsets,
+//! the [schema](Schema) and the values of each variant with a payload in schema order.
+//! Kind tags index the schema, so variants are matched by name when a snapshot
+//! is read by a newer version of the enum.
+use alloc::{
+    string::{String, ToString},
+    vec,
+    vec::Vec,
+};
+use core::{fmt, mem};
+
+/// Magic bytes at the start of a snapshot.
+pub const MAGIC: [u8; 8] = *b"ENUMSARN";
+/// Current format version.
+pub const VERSION: u32 = 3;
+/// Every section starts at a multiple of this.
+pub const SECTION_ALIGN: usize = 16;
+
+const ENDIAN_MARK: u32 = 0x0102_0304;
+const HEADER_LEN: usize = 24;
+const SECTION_ENTRY_LEN: usize = 24;
+
+/// Plain old data, every bit pattern is a valid value and there are no padding bytes.
+///
+/// # Safety
+///
+/// The type must be `#[repr(C)]` or `#[repr(transparent)]` (or primitive),
+/// contain no padding, no pointers and accept any bit pattern.
+pub unsafe trait Pod: Copy + 'static {}
+
+macro_rules! define_pod {
+    ($($ty: ty),*) => {
+        $(unsafe impl Pod for $ty {})*
+    };
+}
+
+define_pod!(u8, u16, u32, u64, u128, usize, i8, i16, i32, i64, i128, isize, f32, f64, ());
+
+unsafe impl<T: Pod, const N: usize> Pod for [T; N] {}
+
+/// Errors of reading a snapshot.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SnapshotError {
+    /// The bytes end before the data does.
+    Truncated,
+    /// The bytes don't start with [`MAGIC`].
+    BadMagic,
+    /// The snapshot is written by an unknown version.
+    UnsupportedVersion(u32),
+    /// The snapshot is written on a machine with another byte order.
+    EndianMismatch,
+    /// A section is not aligned for its item type.
+    Misaligned,
+    /// The sections don't match the arena type.
+    LayoutMismatch,
+    /// A kind tag out of range.
+    InvalidKind,
+    /// An offset pointing outside the values of its variant.
+    InvalidOffset,
+    /// A value of a variant the enum doesn't have any more.
+    UnknownVariant(String),
+    /// The payload type of a variant changed.
+    TypeMismatch {
+        variant: String,
+        stored: String,
+        expected: String,
+    },
+}
+
+impl fmt::Display for SnapshotError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SnapshotError::Truncated => write!(f, "snapshot is truncated"),
+            SnapshotError::BadMagic => write!(f, "not an arena snapshot"),
+            SnapshotError::UnsupportedVersion(v) => {
+                write!(f, "unsupported snapshot version {}", v)
+            }
+            SnapshotError::EndianMismatch => write!(f, "snapshot has another byte order"),
+            SnapshotError::Misaligned => write!(f, "snapshot section is misaligned"),
+            SnapshotError::LayoutMismatch => write!(f, "snapshot doesn't match the arena type"),
+            SnapshotError::InvalidKind => write!(f, "snapshot contains an invalid kind"),
+            SnapshotError::InvalidOffset => write!(f, "snapshot contains an invalid offset"),
+            SnapshotError::UnknownVariant(name) => {
+                write!(f, "snapshot contains a value of unknown variant `{}`", name)
+            }
+            SnapshotError::TypeMismatch {
+                variant,
+                stored,
+                expected,
+            } => write!(
+                f,
+                "payload of variant `{}` is `{}` in the snapshot, expected `{}`",
+                variant, stored, expected
+            ),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for SnapshotError {}
+
+/// View the items as bytes.
+pub fn as_bytes<T: Pod>(items: &[T]) -> &[u8] {
+    // SAFETY: `Pod` types have no padding, so every byte is initialized.
+    unsafe { core::slice::from_raw_parts(items.as_ptr().cast(), mem::size_of_val(items)) }
+}
+
+/// View the bytes as `len` items, fails if the length or the alignment don't fit.
+pub fn cast_slice<T: Pod>(bytes: &[u8], len: usize) -> Result<&[T], SnapshotError> {
+    if Some(bytes.len()) != len.checked_mul(mem::size_of::<T>()) {
+        return Err(SnapshotError::LayoutMismatch);
+    }
+    if mem::size_of::<T>() == 0 {
+        // SAFETY: a slice of zero sized values needs no memory.
+        return Ok(unsafe { core::slice::from_raw_parts(core::ptr::NonNull::dangling().as_ptr(), len) });
+    }
+    if bytes.as_ptr().align_offset(mem::align_of::<T>()) != 0 {
+        return Err(SnapshotError::Misaligned);
+    }
+    // SAFETY: length and alignment are checked, any bit pattern is a valid `Pod`.
+    Ok(unsafe { core::slice::from_raw_parts(bytes.as_ptr().cast(), len) })
+}
+
+/// A section to write.
+pub struct Section<'a> {
+    bytes: &'a [u8],
+    len: usize,
+    item_size: usize,
+}
+
+impl<'a> Section<'a> {
+    pub fn new<T: Pod>(items: &'a [T]) -> Self {
+        Self {
+            bytes: as_bytes(items),
+            len: items.len(),
+            item_size: mem::size_of::<T>(),
+        }
+    }
+}
+
+fn align_up(n: usize) -> usize {
+    n.div_ceil(SECTION_ALIGN) * SECTION_ALIGN
+}
+
+/// Destination of a snapshot, any [`std::io::Write`] with the `std` feature,
+/// otherwise a `Vec<u8>`.
+pub trait SnapshotWrite {
+    type Error;
+
+    fn write_all(&mut self, bytes: &[u8]) -> Result<(), Self::Error>;
+}
+
+#[cfg(feature = "std")]
+impl<W: std::io::Write + ?Sized> SnapshotWrite for W {
+    type Error = std::io::Error;
+
+    fn write_all(&mut self, bytes: &[u8]) -> Result<(), Self::Error> {
+        std::io::Write::write_all(self, bytes)
+    }
+}
+
+#[cfg(not(feature = "std"))]
+impl SnapshotWrite for Vec<u8> {
+    type Error = core::convert::Infallible;
+
+    fn write_all(&mut self, bytes: &[u8]) -> Result<(), Self::Error> {
+        self.extend_from_slice(bytes);
+        Ok(())
+    }
+}
+
+/// Write the header, the section table and the sections.
+pub fn write_sections<W: SnapshotWrite + ?Sized>(
+    w: &mut W,
+    sections: &[Section],
+) -> Result<(), W::Error> {
+    w.write_all(&MAGIC)?;
+    w.write_all(&VERSION.to_ne_bytes())?;
+    w.write_all(&ENDIAN_MARK.to_ne_bytes())?;
+    w.write_all(&(sections.len() as u32).to_ne_bytes())?;
+    w.write_all(&0u32.to_ne_bytes())?;
+
+    let mut pos = align_up(HEADER_LEN + sections.len() * SECTION_ENTRY_LEN);
+    for section in sections {
+        w.write_all(&(pos as u64).to_ne_bytes())?;
+        w.write_all(&(section.len as u64).to_ne_bytes())?;
+        w.write_all(&(section.item_size as u64).to_ne_bytes())?;
+        pos = align_up(pos + section.bytes.len());
+    }
+
+    let mut pos = HEADER_LEN + sections.len() * SECTION_ENTRY_LEN;
+    let padding = [0u8; SECTION_ALIGN];
+    for section in sections {
+        w.write_all(&padding[..align_up(pos) - pos])?;
+        pos = align_up(pos);
+        w.write_all(section.bytes)?;
+        pos += section.bytes.len();
+    }
+    Ok(())
+}
+
+/// Reads sections of a snapshot in place.
+///
+/// The bytes must start at an address aligned to [`SECTION_ALIGN`],
+/// a memory mapped file or [`AlignedBytes`] do.
+#[derive(Clone, Copy, Debug)]
+pub struct SnapshotReader<'b> {
+    bytes: &'b [u8],
+    sections: usize,
+}
+
+fn read_u32(bytes: &[u8], pos: usize) -> Result<u32, SnapshotError> {
+    let b = bytes.get(pos..pos + 4).ok_or(SnapshotError::Truncated)?;
+    Ok(u32::from_ne_bytes(b.try_into().unwrap()))
+}
+
+fn read_u64(bytes: &[u8], pos: usize) -> Result<usize, SnapshotError> {
+    let b = bytes.get(pos..pos + 8).ok_or(SnapshotError::Truncated)?;
+    usize::try_from(u64::from_ne_bytes(b.try_into().unwrap())).map_err(|_| SnapshotError::Truncated)
+}
+
+impl<'b> SnapshotReader<'b> {
+    /// Check the header and the section table.
+    pub fn new(bytes: &'b [u8]) -> Result<Self, SnapshotError> {
+        if bytes.get(..MAGIC.len()).ok_or(SnapshotError::Truncated)? != MAGIC {
+            return Err(SnapshotError::BadMagic);
+        }
+        let version = read_u32(bytes, 8)?;
+        if version != VERSION {
+            return Err(SnapshotError::UnsupportedVersion(version));
+        }
+        if read_u32(bytes, 12)? != ENDIAN_MARK {
+            return Err(SnapshotError::EndianMismatch);
+        }
+        let sections = read_u32(bytes, 16)? as usize;
+        let reader = Self { bytes, sections };
+        for i in 0..sections {
+            reader.section_bytes(i)?;
+        }
+        Ok(reader)
+    }
+
+    /// Number of sections.
+    pub fn sections(&self) -> usize {
+        self.sections
+    }
+
+    /// Returns bytes, item count and item size of the `i`th section.
+    fn section_bytes(&self, i: usize) -> Result<(&'b [u8], usize, usize), SnapshotError> {
+        if i >= self.sections {
+            return Err(SnapshotError::LayoutMismatch);
+        }
+        let entry = HEADER_LEN + i * SECTION_ENTRY_LEN;
+        let offset = read_u64(self.bytes, entry)?;
+        let len = read_u64(self.bytes, entry + 8)?;
+        let item_size = read_u64(self.bytes, entry + 16)?;
+        let end = len
+            .checked_mul(item_size)
+            .and_then(|n| n.checked_add(offset))
+            .ok_or(SnapshotError::Truncated)?;
+        let bytes = self.bytes.get(offset..end).ok_or(SnapshotError::Truncated)?;
+        Ok((bytes, len, item_size))
+    }
+
+    /// The items of the `i`th section.
+    pub fn section<T: Pod>(&self, i: usize) -> Result<&'b [T], SnapshotError> {
+        let (bytes, len, item_size) = self.section_bytes(i)?;
+        if item_size != mem::size_of::<T>() {
+            return Err(SnapshotError::LayoutMismatch);
+        }
+        cast_slice(bytes, len)
+    }
+}
+
+/// Variants of an enum in declaration order, name and payload type name.
+///
+/// The type name of a unit variant is empty.
+pub type Schema<'a> = [(&'a str, &'a str)];
+
+/// Index of the schema section.
+pub const SCHEMA_SECTION: usize = 4;
+
+/// Encode the schema as its section, one `name\ttype\n` line per variant.
+pub fn encode_schema(schema: &Schema) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    for (name, type_name) in schema {
+        bytes.extend_from_slice(name.as_bytes());
+        bytes.push(b'\t');
+        bytes.extend_from_slice(type_name.as_bytes());
+        bytes.push(b'\n');
+    }
+    bytes
+}
+
+/// A value of a removed variant found while reading a snapshot.
+#[derive(Clone, Copy, Debug)]
+pub struct RemovedValue<'b> {
+    /// Index of the value in the arena.
+    pub index: usize,
+    pub variant: &'b str,
+    pub type_name: &'b str,
+    /// Raw bytes of the payload, empty for a unit variant.
+    pub bytes: &'b [u8],
+}
+
+/// Variants of a snapshot matched with the variants of the current enum by name.
+#[derive(Clone, Debug)]
+pub struct SchemaMap<'b> {
+    reader: SnapshotReader<'b>,
+    stored: Vec<(&'b str, &'b str)>,
+    /// Current variant of each stored variant, `None` if it is removed.
+    current: Vec<Option<usize>>,
+    /// Section of each stored variant with a payload.
+    sections: Vec<Option<usize>>,
+}
+
+impl<'b> SchemaMap<'b> {
+    /// Read the stored schema and match it with `schema`.
+    ///
+    /// Fails if a variant keeps its name but changes its payload type.
+    pub fn new(reader: SnapshotReader<'b>, schema: &Schema) -> Result<Self, SnapshotError> {
+        let bytes = reader.section::<u8>(SCHEMA_SECTION)?;
+        let text = core::str::from_utf8(bytes).map_err(|_| SnapshotError::LayoutMismatch)?;
+        let mut stored = Vec::new();
+        for line in text.lines() {
+            stored.push(line.split_once('\t').ok_or(SnapshotError::LayoutMismatch)?);
+        }
+
+        let mut current = Vec::with_capacity(stored.len());
+        let mut sections = Vec::with_capacity(stored.len());
+        let mut section = SCHEMA_SECTION + 1;
+        for (name, type_name) in &stored {
+            let found = schema.iter().position(|(n, _)| n == name);
+            if let Some(i) = found {
+                if schema[i].1 != *type_name {
+                    return Err(SnapshotError::TypeMismatch {
+                        variant: name.to_string(),
+                        stored: type_name.to_string(),
+                        expected: schema[i].1.to_string(),
+                    });
+                }
+            }
+            current.push(found);
+            if type_name.is_empty() {
+                sections.push(None);
+            } else {
+                sections.push(Some(section));
+                section += 1;
+            }
+        }
+        if reader.sections() != section {
+            return Err(SnapshotError::LayoutMismatch);
+        }
+        Ok(Self {
+            reader,
+            stored,
+            current,
+            sections,
+        })
+    }
+
+    /// Number of stored variants.
+    pub fn len(&self) -> usize {
+        self.stored.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.stored.is_empty()
+    }
+
+    /// Current variant of the stored kind tag, `None` if it is removed.
+    pub fn current_of(&self, tag: usize) -> Option<usize> {
+        self.current.get(tag).copied().flatten()
+    }
+
+    /// Values of the current variant `i`, empty if the snapshot doesn't have it.
+    pub fn section<T: Pod>(&self, i: usize) -> Result<&'b [T], SnapshotError> {
+        let tag = self.current.iter().position(|c| *c == Some(i));
+        match tag.and_then(|tag| self.sections[tag]) {
+            Some(section) => self.reader.section(section),
+            None => Ok(&[]),
+        }
+    }
+
+    /// The value at `real_index` of the removed variant `tag`.
+    pub fn removed(
+        &self,
+        index: usize,
+        tag: usize,
+        real_index: usize,
+    ) -> Result<RemovedValue<'b>, SnapshotError> {
+        let (variant, type_name) = *self.stored.get(tag).ok_or(SnapshotError::InvalidKind)?;
+        let bytes = match self.sections[tag] {
+            Some(section) => {
+                let (bytes, len, item_size) = self.reader.section_bytes(section)?;
+                if real_index >= len {
+                    return Err(SnapshotError::InvalidOffset);
+                }
+                &bytes[real_index * item_size..(real_index + 1) * item_size]
+            }
+            None if real_index == 0 => &[],
+            None => return Err(SnapshotError::InvalidOffset),
+        };
+        Ok(RemovedValue {
+            index,
+            variant,
+            type_name,
+            bytes,
+        })
+    }
+}
+
+#[derive(Clone, Copy)]
+#[repr(C, align(16))]
+struct Block([u8; SECTION_ALIGN]);
+
+/// Bytes stored at an address aligned to [`SECTION_ALIGN`],
+/// e.g. a snapshot read from a file instead of mapping it.
+#[derive(Clone)]
+pub struct AlignedBytes {
+    blocks: Vec<Block>,
+    len: usize,
+}
+
+impl AlignedBytes {
+    pub fn new(bytes: &[u8]) -> Self {
+        let mut blocks = vec![Block([0; SECTION_ALIGN]); bytes.len().div_ceil(SECTION_ALIGN)];
+        for (block, chunk) in blocks.iter_mut().zip(bytes.chunks(SECTION_ALIGN)) {
+            block.0[..chunk.len()].copy_from_slice(chunk);
+        }
+        Self {
+            blocks,
+            len: bytes.len(),
+        }
+    }
+}
+
+impl core::ops::Deref for AlignedBytes {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        // SAFETY: blocks are plain bytes, `len` is not larger than all blocks.
+        unsafe { core::slice::from_raw_parts(self.blocks.as_ptr().cast(), self.len) }
+    }
+}
@@ -0,0 +1,116 @@
+use alloc::vec::Vec;
+use core::fmt;
+
+/// Memory usage of the values of one variant.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct VariantStats {
+    /// Name of the variant.
+    pub name: &'static str,
+    /// Number of values.
+    pub count: usize,
+    /// Number of values which fit without reallocating.
+    pub capacity: usize,
+    /// `size_of` the payload.
+    pub payload_size: usize,
+    /// Bytes used by the values, including the back references to their ids.
+    pub bytes_used: usize,
+    /// Bytes allocated for the values, including the back references to their ids.
+    pub bytes_reserved: usize,
+}
+
+/// Memory usage of an arena, returned by `{Name}IdArena::stats`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ArenaStats {
+    /// One entry for each variant, in declaration order.
+    pub variants: Vec<VariantStats>,
+    /// Number of allocated ids.
+    pub index_count: usize,
+    /// Number of ids which fit without reallocating.
+    pub index_capacity: usize,
+    /// Bytes used by the index table.
+    pub index_bytes_used: usize,
+    /// Bytes allocated for the index table.
+    pub index_bytes_reserved: usize,
+    /// Number of generation runs kept for the slots kept by a rollback.
+    pub generation_count: usize,
+    /// Number of generation runs which fit without reallocating.
+    pub generation_capacity: usize,
+    /// Bytes used by the generation runs.
+    pub generation_bytes_used: usize,
+    /// Bytes allocated for the generation runs.
+    pub generation_bytes_reserved: usize,
+}
+
+impl ArenaStats {
+    /// Bytes used by the index table, the generation runs and all values.
+    pub fn bytes_used(&self) -> usize {
+        self.index_bytes_used
+            + self.generation_bytes_used
+            + self.variants.iter().map(|v| v.bytes_used).sum::<usize>()
+    }
+
+    /// Bytes allocated for the index table, the generation runs and all values.
+    pub fn bytes_reserved(&self) -> usize {
+        self.index_bytes_reserved
+            + self.generation_bytes_reserved
+            + self
+                .variants
+                .iter()
+                .map(|v| v.bytes_reserved)
+                .sum::<usize>()
+    }
+}
+
+impl fmt::Display for ArenaStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let width = self
+            .variants
+            .iter()
+            .map(|v| v.name.len())
+            .chain(["variant".len(), "(generations)".len()])
+            .max()
+            .unwrap_or_default();
+        writeln!(
+            f,
+            "{:<width$} {:>10} {:>10} {:>6} {:>12} {:>12}",
+            "variant", "count", "capacity", "size", "used", "reserved"
+        )?;
+        for v in &self.variants {
+            writeln!(
+                f,
+                "{:<width$} {:>10} {:>10} {:>6} {:>12} {:>12}",
+                v.name, v.count, v.capacity, v.payload_size, v.bytes_used, v.bytes_reserved
+            )?;
+        }
+        writeln!(
+            f,
+            "{:<width$} {:>10} {:>10} {:>6} {:>12} {:>12}",
+            "(index)",
+            self.index_count,
+            self.index_capacity,
+            "",
+            self.index_bytes_used,
+            self.index_bytes_reserved
+        )?;
+        writeln!(
+            f,
+            "{:<width$} {:>10} {:>10} {:>6} {:>12} {:>12}",
+            "(generations)",
+            self.generation_count,
+            self.generation_capacity,
+            "",
+            self.generation_bytes_used,
+            self.generation_bytes_reserved
+        )?;
+        write!(
+            f,
+            "{:<width$} {:>10} {:>10} {:>6} {:>12} {:>12}",
+            "(total)",
+            "",
+            "",
+            "",
+            self.bytes_used(),
+            self.bytes_reserved()
+        )
+    }
+}
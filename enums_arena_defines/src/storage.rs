@@ -0,0 +1,163 @@
+use alloc::vec::Vec;
+
+/// Container of the values of one variant.
+///
+/// The arena only appends, removes from the end and swaps the last value into a hole,
+/// so a backend needs [`push`](Self::push), [`pop`](Self::pop) and indexed access.
+/// It also reports its [`capacity`](Self::capacity), which `stats` counts as reserved.
+pub trait Storage: Default {
+    type Item;
+
+    fn push(&mut self, val: Self::Item);
+
+    fn pop(&mut self) -> Option<Self::Item>;
+
+    fn get(&self, index: usize) -> Option<&Self::Item>;
+
+    fn get_mut(&mut self, index: usize) -> Option<&mut Self::Item>;
+
+    fn len(&self) -> usize;
+
+    fn clear(&mut self);
+
+    /// Number of values which fit without allocating.
+    fn capacity(&self) -> usize;
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn truncate(&mut self, len: usize) {
+        while self.len() > len {
+            self.pop();
+        }
+    }
+
+    /// Remove the value at `index`, the last value takes its place.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` is out of bounds.
+    fn swap_remove(&mut self, index: usize) -> Self::Item {
+        let last = self.pop().expect("swap_remove index out of bounds");
+        if index == self.len() {
+            return last;
+        }
+        core::mem::replace(self.get_mut(index).expect("swap_remove index out of bounds"), last)
+    }
+
+    fn reserve(&mut self, additional: usize) {
+        let _ = additional;
+    }
+
+    fn shrink_to_fit(&mut self) {}
+}
+
+/// Storage whose values never move when more values are pushed.
+///
+/// # Safety
+///
+/// [`push_ptr`](Self::push_ptr) and [`Storage::push`] must not move or drop the stored values,
+/// nor create references to them.
+pub unsafe trait StableStorage: Storage {
+    /// Push a value and return a pointer to it, which stays valid
+    /// until the storage is otherwise accessed mutably.
+    fn push_ptr(&mut self, val: Self::Item) -> *mut Self::Item;
+}
+
+impl<T> Storage for Vec<T> {
+    type Item = T;
+
+    fn push(&mut self, val: T) {
+        Vec::push(self, val)
+    }
+
+    fn pop(&mut self) -> Option<T> {
+        Vec::pop(self)
+    }
+
+    fn get(&self, index: usize) -> Option<&T> {
+        <[T]>::get(self, index)
+    }
+
+    fn get_mut(&mut self, index: usize) -> Option<&mut T> {
+        <[T]>::get_mut(self, index)
+    }
+
+    fn len(&self) -> usize {
+        Vec::len(self)
+    }
+
+    fn clear(&mut self) {
+        Vec::clear(self)
+    }
+
+    fn truncate(&mut self, len: usize) {
+        Vec::truncate(self, len)
+    }
+
+    fn swap_remove(&mut self, index: usize) -> T {
+        Vec::swap_remove(self, index)
+    }
+
+    fn capacity(&self) -> usize {
+        Vec::capacity(self)
+    }
+
+    fn reserve(&mut self, additional: usize) {
+        Vec::reserve(self, additional)
+    }
+
+    fn shrink_to_fit(&mut self) {
+        Vec::shrink_to_fit(self)
+    }
+}
+
+#[cfg(feature = "smallvec")]
+impl<A: smallvec::Array> Storage for smallvec::SmallVec<A> {
+    type Item = A::Item;
+
+    fn push(&mut self, val: A::Item) {
+        smallvec::SmallVec::push(self, val)
+    }
+
+    fn pop(&mut self) -> Option<A::Item> {
+        smallvec::SmallVec::pop(self)
+    }
+
+    fn get(&self, index: usize) -> Option<&A::Item> {
+        <[A::Item]>::get(self, index)
+    }
+
+    fn get_mut(&mut self, index: usize) -> Option<&mut A::Item> {
+        <[A::Item]>::get_mut(self, index)
+    }
+
+    fn len(&self) -> usize {
+        smallvec::SmallVec::len(self)
+    }
+
+    fn clear(&mut self) {
+        smallvec::SmallVec::clear(self)
+    }
+
+    fn truncate(&mut self, len: usize) {
+        smallvec::SmallVec::truncate(self, len)
+    }
+
+    fn swap_remove(&mut self, index: usize) -> A::Item {
+        smallvec::SmallVec::swap_remove(self, index)
+    }
+
+    fn capacity(&self) -> usize {
+        smallvec::SmallVec::capacity(self)
+    }
+
+    fn reserve(&mut self, additional: usize) {
+        smallvec::SmallVec::reserve(self, additional)
+    }
+
+    fn shrink_to_fit(&mut self) {
+        smallvec::SmallVec::shrink_to_fit(self)
+    }
+}
@@ -0,0 +1,57 @@
+use quote::quote;
+
+use crate::id_arena::Context;
+
+/// Implement `EnumsArena` for the arena, forwarding to its inherent methods.
+pub fn expand(ctx: &Context) -> proc_macro2::TokenStream {
+    let Context {
+        name,
+        generics,
+        new_generics,
+        id_ident,
+        enum_name_ident,
+        arena_name_ident,
+        ..
+    } = ctx;
+    let ref_ty = crate::map::ref_type(ctx, quote!('__enums_arena_r));
+
+    quote! {
+        impl #new_generics ::enums_arena_defines::EnumsArena for #arena_name_ident #new_generics
+        where HIDE_I: ::enums_arena_defines::Index,
+            HIDE_G: ::enums_arena_defines::Generation,
+        {
+            type Enum = #name #generics;
+            type Id = #id_ident<HIDE_I, HIDE_G>;
+            type Kind = #enum_name_ident;
+            type Ref<'__enums_arena_r> = #ref_ty where Self: '__enums_arena_r;
+
+            fn alloc(&mut self, val: Self::Enum) -> Self::Id {
+                self.alloc(val)
+            }
+
+            fn get_ref(&self, id: Self::Id) -> Option<Self::Ref<'_>> {
+                self.get_ref(id)
+            }
+
+            fn update(&mut self, id: Self::Id, val: Self::Enum) -> Option<()> {
+                self.update(id, val)
+            }
+
+            fn kind_of(&self, id: Self::Id) -> Option<Self::Kind> {
+                self.kind_of(id)
+            }
+
+            fn len(&self) -> usize {
+                self.len()
+            }
+
+            fn clear(&mut self) {
+                self.clear()
+            }
+
+            fn iter(&self) -> impl Iterator<Item = (Self::Id, Self::Ref<'_>)> {
+                self.iter()
+            }
+        }
+    }
+}
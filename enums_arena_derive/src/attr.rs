@@ -0,0 +1,125 @@
+use syn::{parse_quote, Attribute, LitStr, Type};
+
+/// Options given by `#[enums_arena(...)]` on the enum.
+#[derive(Default)]
+pub struct ArenaOptions {
+    /// Generate `{Name}Journal`, an undo/redo wrapper of the arena.
+    pub journal: bool,
+    /// Ids are `(index, generation)`, the kind is only kept in the arena.
+    pub compact_id: bool,
+    /// Implement `Serialize` and `Deserialize`, needs the `serde` feature.
+    pub serde: bool,
+    /// Generate `write_snapshot` and `{Name}ArenaView`, all payloads must be `Pod`.
+    pub snapshot: bool,
+    /// Container of the values of each variant, `_` stands for the payload type.
+    pub storage: Option<Type>,
+    /// Generate `{Name}StableArena`, the storage defaults to `ChunkedVec`.
+    pub stable: bool,
+    /// Generate `{Name}RingArena`, a bounded arena overwriting the oldest values.
+    pub ring: bool,
+    /// Implement `ArenaVariant` for the payload types, which must be distinct.
+    pub typed: bool,
+}
+
+impl ArenaOptions {
+    pub fn parse(attrs: &[Attribute]) -> syn::Result<Self> {
+        let mut options = Self::default();
+        for attr in attrs {
+            if !attr.path().is_ident("enums_arena") {
+                continue;
+            }
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("journal") {
+                    options.journal = true;
+                    return Ok(());
+                }
+                if meta.path.is_ident("serde") {
+                    if cfg!(not(feature = "serde")) {
+                        return Err(meta.error("enable the `serde` feature of enums_arena"));
+                    }
+                    options.serde = true;
+                    return Ok(());
+                }
+                if meta.path.is_ident("snapshot") {
+                    options.snapshot = true;
+                    return Ok(());
+                }
+                if meta.path.is_ident("storage") {
+                    options.storage = Some(meta.value()?.parse()?);
+                    return Ok(());
+                }
+                if meta.path.is_ident("stable") {
+                    options.stable = true;
+                    return Ok(());
+                }
+                if meta.path.is_ident("ring") {
+                    options.ring = true;
+                    return Ok(());
+                }
+                if meta.path.is_ident("typed") {
+                    options.typed = true;
+                    return Ok(());
+                }
+                if meta.path.is_ident("compact_id") {
+                    options.compact_id = true;
+                    return Ok(());
+                }
+                Err(meta.error("unsupported enums_arena option"))
+            })?;
+        }
+        if options.stable && options.storage.is_none() {
+            options.storage = Some(parse_quote!(::enums_arena_defines::ChunkedVec<_>));
+        }
+        if let Some(storage) = &options.storage {
+            for (enabled, name) in [(options.serde, "serde"), (options.snapshot, "snapshot")] {
+                if enabled {
+                    return Err(syn::Error::new_spanned(
+                        storage,
+                        format!("`{}` needs the default `Vec` storage", name),
+                    ));
+                }
+            }
+        }
+        Ok(options)
+    }
+}
+
+/// Options given by `#[arena(...)]` on a variant.
+#[derive(Default)]
+pub struct VariantOptions {
+    /// Name of the group of the variant, see `{Name}Group`.
+    pub group: Option<LitStr>,
+    /// Generate `From` and `TryFrom` between the payload and the enum, `as_{variant}`
+    /// and `into_{variant}`.
+    pub convert: bool,
+}
+
+impl VariantOptions {
+    pub fn parse(attrs: &[Attribute]) -> syn::Result<Self> {
+        let mut options = Self::default();
+        for attr in attrs {
+            if !attr.path().is_ident("arena") {
+                continue;
+            }
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("group") {
+                    let group: LitStr = meta.value()?.parse()?;
+                    if syn::parse_str::<syn::Ident>(&group.value()).is_err() {
+                        return Err(syn::Error::new_spanned(
+                            group,
+                            "group name must be an identifier",
+                        ));
+                    }
+                    options.group = Some(group);
+                    return Ok(());
+                }
+                if meta.path.is_ident("convert") {
+                    options.convert = true;
+                    return Ok(());
+                }
+                Err(meta.error("unsupported arena option"))
+            })?;
+        }
+        Ok(options)
+    }
+}
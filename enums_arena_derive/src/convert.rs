@@ -0,0 +1,93 @@
+use quote::{format_ident, quote};
+
+use crate::id_arena::Context;
+use crate::typed::check_payloads;
+
+/// Generate the conversions of the variants with `#[arena(convert)]`:
+/// `From<Payload>` for the enum, `TryFrom<Enum>` for the payload,
+/// `as_{variant}` and `into_{variant}`.
+pub fn expand(ctx: &Context) -> syn::Result<proc_macro2::TokenStream> {
+    let Context {
+        name,
+        generics,
+        variants,
+        ..
+    } = ctx;
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+
+    let converted: Vec<_> = variants.iter().filter(|v| v.options.convert).collect();
+    if let Some(variant) = converted.iter().find(|v| v.ty.is_none()) {
+        return Err(syn::Error::new_spanned(
+            &variant.ident,
+            "`convert` needs a variant with a payload",
+        ));
+    }
+    check_payloads(
+        generics,
+        converted.iter().copied(),
+        "convert",
+        "`From` and `TryFrom`",
+    )?;
+
+    let mut conversions = Vec::new();
+    let mut field_fn = Vec::new();
+    for variant in converted {
+        let ident = &variant.ident;
+        let Some(ty) = &variant.ty else {
+            continue;
+        };
+        let as_ident = format_ident!("as_{}", variant.ident_case);
+        let into_ident = format_ident!("into_{}", variant.ident_case);
+
+        conversions.push(quote! {
+            impl #impl_generics ::core::convert::From<#ty> for #name #ty_generics #where_clause {
+                fn from(val: #ty) -> Self {
+                    #name::#ident(val)
+                }
+            }
+
+            impl #impl_generics ::core::convert::TryFrom<#name #ty_generics> for #ty #where_clause {
+                /// The value of another variant, given back.
+                type Error = #name #ty_generics;
+
+                fn try_from(val: #name #ty_generics) -> ::core::result::Result<Self, Self::Error> {
+                    #[allow(unreachable_patterns)]
+                    match val {
+                        #name::#ident(val) => Ok(val),
+                        val => Err(val),
+                    }
+                }
+            }
+        });
+        field_fn.push(quote! {
+            #[allow(unused)]
+            pub fn #as_ident(&self) -> Option<&#ty> {
+                #[allow(unreachable_patterns)]
+                match self {
+                    #name::#ident(val) => Some(val),
+                    _ => None,
+                }
+            }
+
+            #[allow(unused)]
+            pub fn #into_ident(self) -> Option<#ty> {
+                #[allow(unreachable_patterns)]
+                match self {
+                    #name::#ident(val) => Some(val),
+                    _ => None,
+                }
+            }
+        });
+    }
+    if field_fn.is_empty() {
+        return Ok(quote! {});
+    }
+
+    Ok(quote! {
+        #(#conversions)*
+
+        impl #impl_generics #name #ty_generics #where_clause {
+            #(#field_fn)*
+        }
+    })
+}
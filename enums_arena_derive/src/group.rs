@@ -0,0 +1,131 @@
+use convert_case::{Case, Casing};
+use quote::{format_ident, quote};
+
+use crate::id_arena::Context;
+
+/// Generate `{Name}Group` from the `#[arena(group = "...")]` of the variants,
+/// `group` of the kind and `iter_group_{group}` of the arena.
+///
+/// Nothing is generated if no variant has a group.
+pub fn expand(ctx: &Context) -> proc_macro2::TokenStream {
+    let Context {
+        name,
+        vis,
+        new_generics,
+        id_ident,
+        enum_name_ident,
+        arena_name_ident,
+        variants,
+        ..
+    } = ctx;
+    let group_ident = format_ident!("{}Group", name);
+    let set_ident = format_ident!("{}KindSet", name);
+
+    // Groups in order of their first variant, with their kinds.
+    let mut groups: Vec<(String, Vec<&syn::Ident>)> = Vec::new();
+    let mut group_match_body = Vec::new();
+    for variant in variants {
+        let ident = &variant.ident;
+        let Some(group) = &variant.options.group else {
+            group_match_body.push(quote! {
+                #enum_name_ident::#ident => None
+            });
+            continue;
+        };
+        let group = group.value();
+        let group_variant = format_ident!("{}", group.to_case(Case::UpperCamel));
+        group_match_body.push(quote! {
+            #enum_name_ident::#ident => Some(#group_ident::#group_variant)
+        });
+        // Names with the same variant, like `input` and `Input`, are one group.
+        match groups
+            .iter_mut()
+            .find(|(name, _)| group_variant == name.to_case(Case::UpperCamel))
+        {
+            Some((_, kinds)) => kinds.push(ident),
+            None => groups.push((group, vec![ident])),
+        }
+    }
+    if groups.is_empty() {
+        return quote! {};
+    }
+
+    let mut group_variants = Vec::new();
+    let mut name_match_body = Vec::new();
+    let mut kinds_match_body = Vec::new();
+    let mut iter_fn = Vec::new();
+    for (group, kinds) in &groups {
+        let group_variant = format_ident!("{}", group.to_case(Case::UpperCamel));
+        let iter_ident = format_ident!("iter_group_{}", group.to_case(Case::Snake));
+        group_variants.push(quote! {
+            #group_variant
+        });
+        name_match_body.push(quote! {
+            #group_ident::#group_variant => #group
+        });
+        kinds_match_body.push(quote! {
+            #group_ident::#group_variant => #set_ident::EMPTY #(.with(#enum_name_ident::#kinds))*
+        });
+        iter_fn.push(quote! {
+            /// Ids of the values in the group, in allocation order.
+            #[allow(unused)]
+            pub fn #iter_ident(&self) -> impl Iterator<Item = #id_ident<HIDE_I, HIDE_G>> + '_ {
+                self.iter_group(#group_ident::#group_variant)
+            }
+        });
+    }
+    let count = groups.len();
+
+    quote! {
+        /// Groups of the variants, declared with `#[arena(group = "...")]`.
+        #[derive(Clone, Copy, Debug, Eq, PartialEq, PartialOrd, Ord, ::core::hash::Hash)]
+        #vis enum #group_ident {
+            #(#group_variants),*
+        }
+
+        impl #group_ident {
+            /// All groups in order of their first variant.
+            #[allow(unused)]
+            pub const ALL: [Self; #count] = [#(#group_ident::#group_variants),*];
+
+            /// Name given in the attribute.
+            #[allow(unused)]
+            pub fn name(self) -> &'static str {
+                match self {
+                    #(#name_match_body),*
+                }
+            }
+
+            /// Kinds of the variants in the group.
+            #[allow(unused)]
+            pub const fn kinds(self) -> #set_ident {
+                match self {
+                    #(#kinds_match_body),*
+                }
+            }
+        }
+
+        impl #enum_name_ident {
+            /// Group of the variant, `None` if it has none.
+            #[allow(unused)]
+            pub const fn group(self) -> Option<#group_ident> {
+                match self {
+                    #(#group_match_body),*
+                }
+            }
+        }
+
+        impl #new_generics #arena_name_ident #new_generics
+        where HIDE_I: ::enums_arena_defines::Index,
+            HIDE_G: ::enums_arena_defines::Generation,
+        {
+            /// Ids of the values in the group, in allocation order.
+            #[allow(unused)]
+            pub fn iter_group(&self, group: #group_ident) -> impl Iterator<Item = #id_ident<HIDE_I, HIDE_G>> + '_ {
+                self.iter_kinds(group.kinds())
+            }
+
+            #(#iter_fn)*
+        }
+    }
+}
@@ -0,0 +1,151 @@
+use proc_macro::Span;
+use quote::{format_ident, quote};
+use syn::{GenericParam, Lifetime, LifetimeParam};
+
+use crate::id_arena::Context;
+
+/// Generate `{Name}Handlers`, one optional closure per variant, and
+/// `dispatch_by_kind` of the arena which runs them variant by variant.
+pub fn expand(ctx: &Context) -> proc_macro2::TokenStream {
+    let Context {
+        name,
+        vis,
+        new_generics,
+        id_ident,
+        enum_name_ident,
+        arena_name_ident,
+        variants,
+        ..
+    } = ctx;
+    let handlers_ident = format_ident!("{}Handlers", name);
+    let counts_ident = format_ident!("{}DispatchCounts", name);
+
+    let mut handlers_generics = new_generics.clone();
+    handlers_generics.params.insert(
+        0,
+        GenericParam::Lifetime(LifetimeParam::new(Lifetime::new(
+            "'__enums_arena_h",
+            Span::call_site().into(),
+        ))),
+    );
+
+    let (_, handlers_ty_generics, _) = handlers_generics.split_for_impl();
+
+    let mut handler_fields = Vec::new();
+    let mut handler_new = Vec::new();
+    let mut on_fn = Vec::new();
+    let mut count_fields = Vec::new();
+    let mut count_names = Vec::new();
+    let mut dispatch = Vec::new();
+
+    for variant in variants {
+        let ident = &variant.ident;
+        let field_ident = format_ident!("{}", variant.ident_case);
+        let on_ident = format_ident!("on_{}", variant.ident_case);
+        let handler_bound = match &variant.ty {
+            Some(ty) => quote! {
+                FnMut(#id_ident<HIDE_I, HIDE_G>, &#ty) + '__enums_arena_h
+            },
+            None => quote! {
+                FnMut(#id_ident<HIDE_I, HIDE_G>) + '__enums_arena_h
+            },
+        };
+        handler_fields.push(quote! {
+            #field_ident: Option<::enums_arena_defines::alloc::boxed::Box<dyn #handler_bound>>
+        });
+        handler_new.push(quote! {
+            #field_ident: None
+        });
+        on_fn.push(quote! {
+            #[allow(unused)]
+            pub fn #on_ident(mut self, f: impl #handler_bound) -> Self {
+                self.#field_ident = Some(::enums_arena_defines::alloc::boxed::Box::new(f));
+                self
+            }
+        });
+        count_fields.push(quote! {
+            pub #field_ident: usize
+        });
+        count_names.push(field_ident.clone());
+
+        match &variant.ty {
+            Some(_) => {
+                let vec_ident = format_ident!("{}_vec", variant.ident_case);
+                let vec_id_ident = format_ident!("{}_vec_id_of", variant.ident_case);
+                dispatch.push(quote! {
+                    if let Some(f) = &mut handlers.#field_ident {
+                        for (real_index, index) in self.#vec_id_ident.iter().enumerate() {
+                            if let Some(val) = ::enums_arena_defines::Storage::get(&self.#vec_ident, real_index) {
+                                f(self.new_id(#enum_name_ident::#ident, *index), val);
+                                counts.#field_ident += 1;
+                            }
+                        }
+                    }
+                });
+            }
+            None => {
+                dispatch.push(quote! {
+                    if let Some(f) = &mut handlers.#field_ident {
+                        for (index, ty) in self.enums_vec_id_kind_of.iter().enumerate() {
+                            if *ty == #enum_name_ident::#ident {
+                                f(self.new_id(*ty, HIDE_I::from_usize(index)));
+                                counts.#field_ident += 1;
+                            }
+                        }
+                    }
+                });
+            }
+        }
+    }
+
+    quote! {
+        /// Number of values each handler of a dispatch processed.
+        #[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+        #vis struct #counts_ident {
+            #(#count_fields),*
+        }
+
+        impl #counts_ident {
+            #[allow(unused)]
+            pub fn total(&self) -> usize {
+                0 #(+ self.#count_names)*
+            }
+        }
+
+        /// One optional handler per variant, see `dispatch_by_kind` of the arena.
+        #vis struct #handlers_ident #handlers_generics {
+            #(#handler_fields),*
+        }
+
+        impl #handlers_generics Default for #handlers_ident #handlers_generics {
+            fn default() -> Self {
+                Self::new()
+            }
+        }
+
+        impl #handlers_generics #handlers_ident #handlers_generics {
+            #[allow(unused)]
+            pub fn new() -> Self {
+                Self {
+                    #(#handler_new),*
+                }
+            }
+
+            #(#on_fn)*
+        }
+
+        impl #new_generics #arena_name_ident #new_generics
+        where HIDE_I: ::enums_arena_defines::Index,
+            HIDE_G: ::enums_arena_defines::Generation,
+        {
+            /// Run the handlers over the values of each variant in storage order,
+            /// variants without a handler are skipped.
+            #[allow(unused)]
+            pub fn dispatch_by_kind<'__enums_arena_h>(&self, handlers: &mut #handlers_ident #handlers_ty_generics) -> #counts_ident {
+                let mut counts = #counts_ident::default();
+                #(#dispatch)*
+                counts
+            }
+        }
+    }
+}
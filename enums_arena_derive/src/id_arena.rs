@@ -4,10 +4,65 @@ use quote::{format_ident, quote};
 use syn::{
     punctuated::Punctuated,
     token::{Gt, Lt},
-    Data, DeriveInput, GenericParam, TypeParam,
+    parse_quote,
+    visit_mut::{self, VisitMut},
+    Data, DeriveInput, GenericParam, Generics, Ident, Type, TypeParam, Visibility,
 };
 
+use crate::attr::{ArenaOptions, VariantOptions};
+
+/// A variant of the user enum.
+pub struct Variant {
+    pub ident: Ident,
+    /// Snake case name, used to build method names.
+    pub ident_case: String,
+    /// Payload type, `None` for a unit variant.
+    pub ty: Option<Type>,
+    pub options: VariantOptions,
+}
+
+/// Everything known about the user enum, shared by the generators.
+pub struct Context<'a> {
+    pub name: &'a Ident,
+    pub vis: &'a Visibility,
+    /// Generics of the user enum.
+    pub generics: &'a Generics,
+    /// Generics of the user enum followed by `HIDE_I, HIDE_G`.
+    pub new_generics: Generics,
+    /// `T: Clone` bounds of the user enum type parameters.
+    pub user_bound: proc_macro2::TokenStream,
+    pub id_ident: Ident,
+    pub enum_name_ident: Ident,
+    /// Integer type of `#[repr]` of `{Name}ExtendEnum`.
+    pub repr_ty: Ident,
+    pub arena_name_ident: Ident,
+    pub variants: Vec<Variant>,
+    pub options: ArenaOptions,
+}
+
+/// Container type of a variant, every `_` in the storage option is replaced by the payload type.
+pub fn storage_type(storage: Option<&Type>, payload: &Type) -> Type {
+    struct Infer<'a>(&'a Type);
+
+    impl VisitMut for Infer<'_> {
+        fn visit_type_mut(&mut self, ty: &mut Type) {
+            if let Type::Infer(_) = ty {
+                *ty = self.0.clone();
+            } else {
+                visit_mut::visit_type_mut(self, ty);
+            }
+        }
+    }
+
+    let mut ty = storage
+        .cloned()
+        .unwrap_or_else(|| parse_quote!(::enums_arena_defines::alloc::vec::Vec<_>));
+    Infer(payload).visit_type_mut(&mut ty);
+    ty
+}
+
 pub fn enums_id_arena_to(ast: &DeriveInput) -> syn::Result<TokenStream> {
+    let options = ArenaOptions::parse(&ast.attrs)?;
     let name = &ast.ident;
     let variants = match &ast.data {
         Data::Enum(v) => &v.variants,
@@ -57,44 +112,59 @@ pub fn enums_id_arena_to(ast: &DeriveInput) -> syn::Result<TokenStream> {
     let mut alloc_match_body = Vec::new();
     let mut clear_vecs = Vec::new();
     let mut update_match_body = Vec::new();
+    let mut push_match_body = Vec::new();
+    let mut pop_match_body = Vec::new();
+    let mut replace_match_body = Vec::new();
+    let mut reserve_match_body = Vec::new();
+    let mut shrink_vecs = Vec::new();
+    let mut variant_stats = Vec::new();
 
     let mut field_fn = Vec::new();
+    let mut variant_infos = Vec::new();
 
     let enum_name_ident = format_ident!("{}ExtendEnum", name);
+    let range_ident = format_ident!("{}IdRange", name);
 
     let n = variants.len() as u64;
-    let repr = if n <= u8::MAX as u64 {
-        quote! {
-            #[repr(u8)]
-        }
+    let repr_ty = if n <= u8::MAX as u64 {
+        format_ident!("u8")
     } else if n <= u16::MAX as u64 {
-        quote! {
-            #[repr(u16)]
-        }
+        format_ident!("u16")
     } else if n <= u32::MAX as u64 {
-        quote! {
-            #[repr(u32)]
-        }
+        format_ident!("u32")
     } else {
-        quote! {
-            #[repr(u64)]
-        }
+        format_ident!("u64")
+    };
+    let repr = quote! {
+        #[repr(#repr_ty)]
     };
 
     for variant in variants {
         let ident = &variant.ident;
         let ident_case = format!("{}", ident).to_case(convert_case::Case::Snake);
         let fields = &variant.fields;
+        let variant_options = VariantOptions::parse(&variant.attrs)?;
 
-        let is_empty = fields.is_empty();
         let ret_ty = match fields.len() {
             0 => {
+                variant_infos.push(Variant {
+                    ident: ident.clone(),
+                    ident_case: ident_case.clone(),
+                    ty: None,
+                    options: variant_options,
+                });
                 quote! {
                     ()
                 }
             }
             1 => {
                 let field = fields.iter().next().unwrap();
+                variant_infos.push(Variant {
+                    ident: ident.clone(),
+                    ident_case: ident_case.clone(),
+                    ty: Some(field.ty.clone()),
+                    options: variant_options,
+                });
                 quote! {
                     #field
                 }
@@ -108,16 +178,47 @@ pub fn enums_id_arena_to(ast: &DeriveInput) -> syn::Result<TokenStream> {
         };
         let alloc_ident = format_ident!("alloc_{}", ident_case);
         let vec_ident = format_ident!("{}_vec", ident_case);
+        let vec_id_ident = format_ident!("{}_vec_id_of", ident_case);
         let get_ident = format_ident!("get_{}", ident_case);
         let get_mut_ident = format_ident!("get_{}_mut", ident_case);
+        let alloc_many_ident = format_ident!("alloc_{}_many", ident_case);
+        let alloc_mut_ident = format_ident!("alloc_{}_mut", ident_case);
+        let reserve_ident = format_ident!("reserve_{}", ident_case);
+        let capacity_ident = format_ident!("capacity_{}", ident_case);
 
         extend_enum_fields.push(quote! {#ident});
-        if !is_empty {
+        let ident_str = ident.to_string();
+        if let Some(field) = fields.iter().next() {
+            let storage_ty = storage_type(options.storage.as_ref(), &field.ty);
             vec_defines.push(quote! {
-                #vec_ident: Vec<#ret_ty>
+                #vec_ident: #storage_ty,
+                #vec_id_ident: ::enums_arena_defines::alloc::vec::Vec<HIDE_I>
             });
             clear_vecs.push(quote! {
-                self.#vec_ident.clear()
+                ::enums_arena_defines::Storage::clear(&mut self.#vec_ident);
+                self.#vec_id_ident.clear()
+            });
+            variant_stats.push(quote! {
+                ::enums_arena_defines::VariantStats {
+                    name: #ident_str,
+                    count: ::enums_arena_defines::Storage::len(&self.#vec_ident),
+                    capacity: ::enums_arena_defines::Storage::capacity(&self.#vec_ident),
+                    payload_size: ::core::mem::size_of::<#ret_ty>(),
+                    bytes_used: ::enums_arena_defines::Storage::len(&self.#vec_ident) * ::core::mem::size_of::<#ret_ty>()
+                        + self.#vec_id_ident.len() * ::core::mem::size_of::<HIDE_I>(),
+                    bytes_reserved: ::enums_arena_defines::Storage::capacity(&self.#vec_ident) * ::core::mem::size_of::<#ret_ty>()
+                        + self.#vec_id_ident.capacity() * ::core::mem::size_of::<HIDE_I>(),
+                }
+            });
+            shrink_vecs.push(quote! {
+                ::enums_arena_defines::Storage::shrink_to_fit(&mut self.#vec_ident);
+                self.#vec_id_ident.shrink_to_fit()
+            });
+            reserve_match_body.push(quote! {
+                #enum_name_ident::#ident => {
+                    ::enums_arena_defines::Storage::reserve(&mut self.#vec_ident, additional);
+                    self.#vec_id_ident.reserve(additional);
+                }
             });
             alloc_match_body.push(quote! {
                 #name::#ident(val) => self.#alloc_ident(val)
@@ -125,7 +226,7 @@ pub fn enums_id_arena_to(ast: &DeriveInput) -> syn::Result<TokenStream> {
             get_cloned_match_body.push(quote! {
                 #enum_name_ident::#ident => {
                     Some(#name::#ident #user_generics(
-                        self.#vec_ident.get(ty_index.to_usize()).cloned()?
+                        ::enums_arena_defines::Storage::get(&self.#vec_ident, real_index).cloned()?
                     ))
                 }
             });
@@ -134,41 +235,107 @@ pub fn enums_id_arena_to(ast: &DeriveInput) -> syn::Result<TokenStream> {
                     if ty != #enum_name_ident::#ident {
                         return None;
                     }
-                    self.#vec_ident[real_index] = val;
+                    *::enums_arena_defines::Storage::get_mut(&mut self.#vec_ident, real_index)? = val;
+                }
+            });
+            push_match_body.push(quote! {
+                #name::#ident(val) => {
+                    let real_index = HIDE_I::from_usize(::enums_arena_defines::Storage::len(&self.#vec_ident));
+                    ::enums_arena_defines::Storage::push(&mut self.#vec_ident, val);
+                    self.#vec_id_ident.push(index);
+                    (#enum_name_ident::#ident, real_index)
+                }
+            });
+            pop_match_body.push(quote! {
+                #enum_name_ident::#ident => {
+                    let val = ::enums_arena_defines::Storage::swap_remove(&mut self.#vec_ident, real_index);
+                    self.#vec_id_ident.swap_remove(real_index);
+                    if let Some(moved) = self.#vec_id_ident.get(real_index) {
+                        self.enums_vec_id_offset_of[moved.to_usize()] = HIDE_I::from_usize(real_index);
+                    }
+                    #name::#ident(val)
+                }
+            });
+            replace_match_body.push(quote! {
+                #name::#ident(val) if ty == #enum_name_ident::#ident => {
+                    let old = ::core::mem::replace(::enums_arena_defines::Storage::get_mut(&mut self.#vec_ident, real_index)?, val);
+                    Some(#name::#ident(old))
                 }
             });
             field_fn.push(quote!{
                 #[allow(unused)]
                 pub fn #alloc_ident(&mut self, val: #ret_ty) -> #id_ident<HIDE_I, HIDE_G> {
                     let index = HIDE_I::from_usize(self.enums_vec_id_offset_of.len());
-                    let real_index = HIDE_I::from_usize(self.#vec_ident.len());
-                    self.#vec_ident.push(val);
+                    let real_index = HIDE_I::from_usize(::enums_arena_defines::Storage::len(&self.#vec_ident));
+                    ::enums_arena_defines::Storage::push(&mut self.#vec_ident, val);
+                    self.#vec_id_ident.push(index);
                     self.enums_vec_id_offset_of.push(real_index);
-                    (#enum_name_ident::#ident, index, self.g)
+                    self.enums_vec_id_kind_of.push(#enum_name_ident::#ident);
+                    self.new_id(#enum_name_ident::#ident, index)
                 }
 
+                /// Like the plain alloc, also returns the stored value.
                 #[allow(unused)]
-                pub fn #get_ident(&self, id: #id_ident<HIDE_I, HIDE_G>) -> Option<& #ret_ty> {
-                    let (ty, index, g) = id;
-                    if g != self.g {
-                        return None;
+                pub fn #alloc_mut_ident(&mut self, val: #ret_ty) -> (#id_ident<HIDE_I, HIDE_G>, &mut #ret_ty) {
+                    let id = self.#alloc_ident(val);
+                    let real_index = ::enums_arena_defines::Storage::len(&self.#vec_ident) - 1;
+                    let val = ::enums_arena_defines::Storage::get_mut(&mut self.#vec_ident, real_index);
+                    (id, val.expect("value is just pushed"))
+                }
+
+                #[allow(unused)]
+                pub fn #reserve_ident(&mut self, additional: usize) {
+                    self.enums_vec_id_offset_of.reserve(additional);
+                    self.enums_vec_id_kind_of.reserve(additional);
+                    ::enums_arena_defines::Storage::reserve(&mut self.#vec_ident, additional);
+                    self.#vec_id_ident.reserve(additional);
+                }
+
+                #[allow(unused)]
+                pub fn #capacity_ident(&self) -> usize {
+                    ::enums_arena_defines::Storage::capacity(&self.#vec_ident)
+                }
+
+                #[allow(unused)]
+                pub fn #alloc_many_ident<HIDE_T: IntoIterator<Item = #ret_ty>>(&mut self, iter: HIDE_T) -> #range_ident<HIDE_I, HIDE_G> {
+                    let iter = iter.into_iter();
+                    let (lower, _) = iter.size_hint();
+                    self.enums_vec_id_offset_of.reserve(lower);
+                    self.enums_vec_id_kind_of.reserve(lower);
+                    ::enums_arena_defines::Storage::reserve(&mut self.#vec_ident, lower);
+                    self.#vec_id_ident.reserve(lower);
+
+                    let start = HIDE_I::from_usize(self.enums_vec_id_offset_of.len());
+                    for val in iter {
+                        let index = HIDE_I::from_usize(self.enums_vec_id_offset_of.len());
+                        let real_index = HIDE_I::from_usize(::enums_arena_defines::Storage::len(&self.#vec_ident));
+                        ::enums_arena_defines::Storage::push(&mut self.#vec_ident, val);
+                        self.#vec_id_ident.push(index);
+                        self.enums_vec_id_offset_of.push(real_index);
+                        self.enums_vec_id_kind_of.push(#enum_name_ident::#ident);
                     }
-                    let real_index = self.enums_vec_id_offset_of[index.to_usize()].to_usize();
+                    #range_ident {
+                        ty: #enum_name_ident::#ident,
+                        start,
+                        end: HIDE_I::from_usize(self.enums_vec_id_offset_of.len()),
+                        g: self.g,
+                    }
+                }
+
+                #[allow(unused)]
+                pub fn #get_ident(&self, id: #id_ident<HIDE_I, HIDE_G>) -> Option<& #ret_ty> {
+                    let (ty, real_index) = self.slot(id)?;
                     if let #enum_name_ident::#ident = ty {
-                        return Some(&self.#vec_ident[real_index]);
+                        return ::enums_arena_defines::Storage::get(&self.#vec_ident, real_index);
                     }
                     None
                 }
 
                 #[allow(unused)]
                 pub fn #get_mut_ident(&mut self, id: #id_ident<HIDE_I, HIDE_G>) -> Option<&mut #ret_ty> {
-                    let (ty, index, g) = id;
-                    if g != self.g {
-                        return None;
-                    }
-                    let real_index = self.enums_vec_id_offset_of[index.to_usize()].to_usize();
+                    let (ty, real_index) = self.slot(id)?;
                     if let #enum_name_ident::#ident = ty {
-                        return Some(&mut self.#vec_ident[real_index]);
+                        return ::enums_arena_defines::Storage::get_mut(&mut self.#vec_ident, real_index);
                     }
                     None
                 }
@@ -179,7 +346,7 @@ pub fn enums_id_arena_to(ast: &DeriveInput) -> syn::Result<TokenStream> {
             });
             get_cloned_match_body.push(quote! {
                 #enum_name_ident::#ident => {
-                    match ty_index.to_usize() {
+                    match real_index {
                         0 => Some(#name::#ident #user_generics),
                         _ => None,
                     }
@@ -192,12 +359,59 @@ pub fn enums_id_arena_to(ast: &DeriveInput) -> syn::Result<TokenStream> {
                     }
                 }
             });
+            push_match_body.push(quote! {
+                #name::#ident => (#enum_name_ident::#ident, HIDE_I::from_usize(0))
+            });
+            pop_match_body.push(quote! {
+                #enum_name_ident::#ident => #name::#ident
+            });
+            reserve_match_body.push(quote! {
+                #enum_name_ident::#ident => {}
+            });
+            variant_stats.push(quote! {
+                ::enums_arena_defines::VariantStats {
+                    name: #ident_str,
+                    count: self
+                        .enums_vec_id_kind_of
+                        .iter()
+                        .filter(|ty| **ty == #enum_name_ident::#ident)
+                        .count(),
+                    capacity: 0,
+                    payload_size: 0,
+                    bytes_used: 0,
+                    bytes_reserved: 0,
+                }
+            });
+            replace_match_body.push(quote! {
+                #name::#ident if ty == #enum_name_ident::#ident => Some(#name::#ident)
+            });
             field_fn.push(quote! {
                 #[allow(unused)]
                 pub fn #alloc_ident(&mut self) -> #id_ident<HIDE_I, HIDE_G> {
                     let index = HIDE_I::from_usize(self.enums_vec_id_offset_of.len());
                     self.enums_vec_id_offset_of.push(HIDE_I::from_usize(0));
-                    (#enum_name_ident::#ident, index, self.g)
+                    self.enums_vec_id_kind_of.push(#enum_name_ident::#ident);
+                    self.new_id(#enum_name_ident::#ident, index)
+                }
+
+                #[allow(unused)]
+                pub fn #reserve_ident(&mut self, additional: usize) {
+                    self.enums_vec_id_offset_of.reserve(additional);
+                    self.enums_vec_id_kind_of.reserve(additional);
+                }
+
+                #[allow(unused)]
+                pub fn #alloc_many_ident(&mut self, n: usize) -> #range_ident<HIDE_I, HIDE_G> {
+                    let start = HIDE_I::from_usize(self.enums_vec_id_offset_of.len());
+                    let end = self.enums_vec_id_offset_of.len() + n;
+                    self.enums_vec_id_offset_of.resize(end, HIDE_I::from_usize(0));
+                    self.enums_vec_id_kind_of.resize(end, #enum_name_ident::#ident);
+                    #range_ident {
+                        ty: #enum_name_ident::#ident,
+                        start,
+                        end: HIDE_I::from_usize(end),
+                        g: self.g,
+                    }
                 }
             });
         }
@@ -229,24 +443,285 @@ pub fn enums_id_arena_to(ast: &DeriveInput) -> syn::Result<TokenStream> {
     }));
 
     let arena_name_ident = format_ident!("{}IdArena", name);
+    let checkpoint_ident = format_ident!("{}Checkpoint", name);
+
+    let ctx = Context {
+        name,
+        vis: vis_control,
+        generics,
+        new_generics: new_generics.clone(),
+        user_bound: user_bound.clone(),
+        id_ident: id_ident.clone(),
+        enum_name_ident: enum_name_ident.clone(),
+        repr_ty,
+        arena_name_ident: arena_name_ident.clone(),
+        variants: variant_infos,
+        options,
+    };
+    let kind_part = crate::kind::expand(&ctx);
+    let kind_set_part = crate::kind_set::expand(&ctx);
+    let group_part = crate::group::expand(&ctx);
+    let visitor_part = crate::visitor::expand(&ctx);
+    let handlers_part = crate::handlers::expand(&ctx);
+    let map_part = crate::map::expand(&ctx);
+    let arena_trait_part = crate::arena_trait::expand(&ctx);
+    let journal_part = if ctx.options.journal {
+        crate::journal::expand(&ctx)
+    } else {
+        quote! {}
+    };
+    let stable_part = if ctx.options.stable {
+        crate::stable::expand(&ctx)
+    } else {
+        quote! {}
+    };
+    let convert_part = crate::convert::expand(&ctx)?;
+    let typed_part = if ctx.options.typed {
+        crate::typed::expand(&ctx)?
+    } else {
+        quote! {}
+    };
+    let ring_part = if ctx.options.ring {
+        crate::ring::expand(&ctx)
+    } else {
+        quote! {}
+    };
+    let snapshot_part = if ctx.options.snapshot {
+        crate::snapshot::expand(&ctx)
+    } else {
+        quote! {}
+    };
+    let (kind_attrs, serde_part) = if ctx.options.serde {
+        (crate::serde_impl::kind_attrs(), crate::serde_impl::expand(&ctx))
+    } else {
+        (quote! {}, quote! {})
+    };
+    let (id_define, id_fn, range_id) = if ctx.options.compact_id {
+        (
+            quote! {
+                type #id_ident<I, G> = (I, G);
+            },
+            quote! {
+                fn new_id(&self, _ty: #enum_name_ident, index: HIDE_I) -> #id_ident<HIDE_I, HIDE_G> {
+                    (index, self.generation_of(index.to_usize()))
+                }
+
+                fn id_parts(id: #id_ident<HIDE_I, HIDE_G>) -> (HIDE_I, HIDE_G) {
+                    id
+                }
+            },
+            quote! {
+                (index, self.g)
+            },
+        )
+    } else {
+        (
+            quote! {
+                type #id_ident<I, G> = (#enum_name_ident, I, G);
+            },
+            quote! {
+                fn new_id(&self, ty: #enum_name_ident, index: HIDE_I) -> #id_ident<HIDE_I, HIDE_G> {
+                    (ty, index, self.generation_of(index.to_usize()))
+                }
+
+                fn id_parts(id: #id_ident<HIDE_I, HIDE_G>) -> (HIDE_I, HIDE_G) {
+                    (id.1, id.2)
+                }
+
+                #[allow(unused)]
+                pub fn ty(&self, id: #id_ident<HIDE_I, HIDE_G>) -> #enum_name_ident {
+                    match self.slot(id) {
+                        Some((ty, _)) => ty,
+                        None => id.0,
+                    }
+                }
+            },
+            quote! {
+                (self.ty, index, self.g)
+            },
+        )
+    };
+
     let impl_part = quote! {
 
+        /// Returns kind and offset of the slot if the id is alive.
+        fn slot(&self, id: #id_ident<HIDE_I, HIDE_G>) -> Option<(#enum_name_ident, usize)> {
+            let (index, g) = Self::id_parts(id);
+            let index = index.to_usize();
+            if g != self.generation_of(index) {
+                return None;
+            }
+            let ty = *self.enums_vec_id_kind_of.get(index)?;
+            let real_index = self.enums_vec_id_offset_of.get(index)?.to_usize();
+            Some((ty, real_index))
+        }
+
+        /// Generation of the slot `index`, slots kept by a rollback keep their own.
+        fn generation_of(&self, index: usize) -> HIDE_G {
+            self.enums_vec_id_g_of.get(index, self.g)
+        }
+
+        /// Move to a new generation, the slots allocated so far keep their own.
+        fn next_generation(&mut self) {
+            self.enums_vec_id_g_of.truncate(self.enums_vec_id_offset_of.len(), self.g);
+            self.g.add();
+        }
+
+        /// Generation of the last slot before `len`, which a checkpoint of `len` slots remembers.
+        fn generation_before(&self, len: usize) -> HIDE_G {
+            match len.checked_sub(1) {
+                Some(index) => self.generation_of(index),
+                None => self.g,
+            }
+        }
+
+        /// Moves the value into the storage of its variant, returns kind and offset.
+        fn push_value(&mut self, index: HIDE_I, val: #name #generics) -> (#enum_name_ident, HIDE_I) {
+            match val {
+                #(#push_match_body),*
+            }
+        }
+
+        /// Moves the value out of the storage of its variant.
+        ///
+        /// The last value of the storage is moved into the hole.
+        fn take_value(&mut self, ty: #enum_name_ident, real_index: usize) -> #name #generics {
+            match ty {
+                #(#pop_match_body),*
+            }
+        }
+
+        /// Removes the last allocated value.
+        fn pop(&mut self) -> Option<#name #generics> {
+            let ty = self.enums_vec_id_kind_of.pop()?;
+            let real_index = self.enums_vec_id_offset_of.pop()?.to_usize();
+            Some(self.take_value(ty, real_index))
+        }
+
+        #id_fn
+
+        #[allow(unused)]
+        pub fn kind_of(&self, id: #id_ident<HIDE_I, HIDE_G>) -> Option<#enum_name_ident> {
+            Some(self.slot(id)?.0)
+        }
+
+        #[allow(unused)]
+        pub fn contains(&self, id: #id_ident<HIDE_I, HIDE_G>) -> bool {
+            self.slot(id).is_some()
+        }
+
         #[allow(unused)]
-        pub fn ty(&self, id: #id_ident<HIDE_I, HIDE_G>) -> #enum_name_ident {
-            id.0
+        pub fn id_at(&self, index: HIDE_I) -> Option<#id_ident<HIDE_I, HIDE_G>> {
+            let ty = *self.enums_vec_id_kind_of.get(index.to_usize())?;
+            Some(self.new_id(ty, index))
         }
 
         #[allow(unused)]
         pub fn len(&self) -> usize {
             self.enums_vec_id_offset_of.len()
         }
+        #[allow(unused)]
+        pub fn is_empty(&self) -> bool {
+            self.enums_vec_id_offset_of.is_empty()
+        }
+
         #[allow(unused)]
         pub fn clear(&mut self) {
             self.g.add();
             self.enums_vec_id_offset_of.clear();
+            self.enums_vec_id_kind_of.clear();
+            self.enums_vec_id_g_of.clear();
             #(#clear_vecs);*
         }
 
+        #[allow(unused)]
+        pub fn with_capacity(capacity: usize) -> Self
+        where Self: Default
+        {
+            let mut arena = Self::default();
+            arena.enums_vec_id_offset_of.reserve(capacity);
+            arena.enums_vec_id_kind_of.reserve(capacity);
+            arena
+        }
+
+        /// Returns the number of ids the arena can hold without reallocating.
+        #[allow(unused)]
+        pub fn capacity(&self) -> usize {
+            self.enums_vec_id_offset_of.capacity()
+        }
+
+        /// Reserve capacity for at least the given number of values of each kind.
+        #[allow(unused)]
+        pub fn reserve_for(&mut self, additional: &[(#enum_name_ident, usize)]) {
+            let total = additional.iter().map(|(_, n)| n).sum();
+            self.enums_vec_id_offset_of.reserve(total);
+            self.enums_vec_id_kind_of.reserve(total);
+            for &(ty, additional) in additional {
+                match ty {
+                    #(#reserve_match_body),*
+                }
+            }
+        }
+
+        #[allow(unused)]
+        pub fn shrink_to_fit(&mut self) {
+            self.enums_vec_id_offset_of.shrink_to_fit();
+            self.enums_vec_id_kind_of.shrink_to_fit();
+            self.enums_vec_id_g_of.shrink_to_fit();
+            #(#shrink_vecs);*
+        }
+
+        /// Returns the memory usage of each variant, the index table and the generation runs.
+        #[allow(unused)]
+        pub fn stats(&self) -> ::enums_arena_defines::ArenaStats {
+            let index_size = ::core::mem::size_of::<HIDE_I>();
+            let kind_size = ::core::mem::size_of::<#enum_name_ident>();
+            let run_size = ::core::mem::size_of::<(usize, HIDE_G)>();
+            ::enums_arena_defines::ArenaStats {
+                variants: ::enums_arena_defines::alloc::vec![#(#variant_stats),*],
+                index_count: self.enums_vec_id_offset_of.len(),
+                index_capacity: self.enums_vec_id_offset_of.capacity(),
+                index_bytes_used: self.enums_vec_id_offset_of.len() * (index_size + kind_size),
+                index_bytes_reserved: self.enums_vec_id_offset_of.capacity() * index_size
+                    + self.enums_vec_id_kind_of.capacity() * kind_size,
+                generation_count: self.enums_vec_id_g_of.runs().len(),
+                generation_capacity: self.enums_vec_id_g_of.capacity(),
+                generation_bytes_used: self.enums_vec_id_g_of.runs().len() * run_size,
+                generation_bytes_reserved: self.enums_vec_id_g_of.capacity() * run_size,
+            }
+        }
+
+        #[allow(unused)]
+        pub fn checkpoint(&self) -> #checkpoint_ident<HIDE_G> {
+            let len = self.enums_vec_id_offset_of.len();
+            #checkpoint_ident {
+                g: self.generation_before(len),
+                len,
+            }
+        }
+
+        /// Drop every value allocated after the checkpoint.
+        ///
+        /// The arena moves to a new generation, so ids of the dropped values find nothing
+        /// even after their slots are allocated again.
+        #[allow(unused)]
+        pub fn rollback_to(&mut self, checkpoint: #checkpoint_ident<HIDE_G>) -> Option<()> {
+            let len = self.enums_vec_id_offset_of.len();
+            if checkpoint.len > len
+                || (checkpoint.len > 0 && checkpoint.g != self.generation_before(checkpoint.len))
+            {
+                return None;
+            }
+            if checkpoint.len == len {
+                return Some(());
+            }
+            while self.enums_vec_id_offset_of.len() > checkpoint.len {
+                self.pop();
+            }
+            self.next_generation();
+            Some(())
+        }
+
         #[allow(unused)]
         pub fn alloc(&mut self, val: #name #generics) -> #id_ident<HIDE_I, HIDE_G> {
             match val {
@@ -256,11 +731,7 @@ pub fn enums_id_arena_to(ast: &DeriveInput) -> syn::Result<TokenStream> {
 
         #[allow(unused)]
         pub fn update(&mut self, id: #id_ident<HIDE_I, HIDE_G>, val: #name #generics) -> Option<()> {
-            let (ty, index, g) = id;
-            if g != self.g {
-                return None;
-            }
-            let real_index = self.enums_vec_id_offset_of[index.to_usize()].to_usize();
+            let (ty, real_index) = self.slot(id)?;
 
             match val {
                 #(#update_match_body),*
@@ -268,23 +739,106 @@ pub fn enums_id_arena_to(ast: &DeriveInput) -> syn::Result<TokenStream> {
             Some(())
         }
 
+        #[allow(unused)]
+        pub fn replace(&mut self, id: #id_ident<HIDE_I, HIDE_G>, val: #name #generics) -> Option<#name #generics> {
+            let (ty, real_index) = self.slot(id)?;
+            let (index, _) = Self::id_parts(id);
+
+            match val {
+                #(#replace_match_body,)*
+                val => {
+                    let old = self.take_value(ty, real_index);
+                    let (ty, real_index) = self.push_value(index, val);
+                    self.enums_vec_id_kind_of[index.to_usize()] = ty;
+                    self.enums_vec_id_offset_of[index.to_usize()] = real_index;
+                    Some(old)
+                }
+            }
+        }
+
         #(#field_fn)*
     };
 
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+    let (_, arena_ty_generics, _) = new_generics.split_for_impl();
+
     let res = quote! {
-        #[derive(Clone, Copy, Debug, Eq, PartialEq, ::std::hash::Hash)]
+        #[derive(Clone, Copy, Debug, Eq, PartialEq, PartialOrd, Ord, ::core::hash::Hash)]
         #repr
+        #kind_attrs
         #vis_control enum #enum_name_ident {
             #(#extend_enum_fields),*
         }
 
-        type #id_ident<I, G> = (#enum_name_ident, I, G);
+        #id_define
+
+        /// Ids allocated one after another with the same kind.
+        #[derive(Clone, Copy, Debug, Eq, PartialEq)]
+        #vis_control struct #range_ident<I, G> {
+            ty: #enum_name_ident,
+            start: I,
+            end: I,
+            g: G,
+        }
 
+        impl<HIDE_I, HIDE_G> #range_ident<HIDE_I, HIDE_G>
+        where HIDE_I: ::enums_arena_defines::Index,
+            HIDE_G: ::enums_arena_defines::Generation,
+        {
+            #[allow(unused)]
+            pub fn kind(&self) -> #enum_name_ident {
+                self.ty
+            }
+
+            #[allow(unused)]
+            pub fn len(&self) -> usize {
+                self.end.to_usize() - self.start.to_usize()
+            }
+
+            #[allow(unused)]
+            pub fn is_empty(&self) -> bool {
+                self.start == self.end
+            }
+
+            /// Returns the `n`th id of the range.
+            #[allow(unused)]
+            pub fn get(&self, n: usize) -> Option<#id_ident<HIDE_I, HIDE_G>> {
+                if n >= self.len() {
+                    return None;
+                }
+                let index = HIDE_I::from_usize(self.start.to_usize() + n);
+                Some(#range_id)
+            }
+
+            #[allow(unused)]
+            pub fn iter(&self) -> impl Iterator<Item = #id_ident<HIDE_I, HIDE_G>> {
+                let range = *self;
+                (0..self.len()).filter_map(move |n| range.get(n))
+            }
+        }
+
+        #[derive(Clone, Copy, Debug, Eq, PartialEq)]
+        #vis_control struct #checkpoint_ident<G> {
+            g: G,
+            len: usize,
+        }
+
+        /// Arena of the values of the enum, each variant is stored in its own `Vec`.
+        ///
+        #[doc = concat!("Every id costs an `I` offset and a [`", stringify!(#enum_name_ident), "`] tag, every value with a payload")]
+        /// also costs an `I` back reference to its id. The tags find the kind of an index
+        /// without an id, which iterating in allocation order, `id_at`, `compact_id` and
+        /// snapshots need. The back references let `replace` and `rollback_to` fill the hole
+        /// left by a value with the last value of its variant. Slots kept by a rollback also
+        /// remember their generation, one `(usize, G)` run per rollback which kept slots of
+        /// another generation. `stats` counts all of them.
         #[derive(Default)]
         #vis_control struct #arena_name_ident #new_generics {
             g: HIDE_G,
 
-            enums_vec_id_offset_of: Vec<HIDE_I>,
+            enums_vec_id_offset_of: ::enums_arena_defines::alloc::vec::Vec<HIDE_I>,
+            enums_vec_id_kind_of: ::enums_arena_defines::alloc::vec::Vec<#enum_name_ident>,
+            enums_vec_id_g_of: ::enums_arena_defines::Generations<HIDE_G>,
             #(#vec_defines),*
         }
 
@@ -295,24 +849,62 @@ pub fn enums_id_arena_to(ast: &DeriveInput) -> syn::Result<TokenStream> {
             #impl_part
         }
 
+        impl #new_generics ::core::iter::Extend<#name #generics> for #arena_name_ident #new_generics
+        where HIDE_I: ::enums_arena_defines::Index,
+            HIDE_G: ::enums_arena_defines::Generation,
+        {
+            fn extend<HIDE_T: IntoIterator<Item = #name #generics>>(&mut self, iter: HIDE_T) {
+                let iter = iter.into_iter();
+                let (lower, _) = iter.size_hint();
+                self.enums_vec_id_offset_of.reserve(lower);
+                self.enums_vec_id_kind_of.reserve(lower);
+                for val in iter {
+                    self.alloc(val);
+                }
+            }
+        }
+
         impl #new_generics #arena_name_ident #new_generics
         where HIDE_I: ::enums_arena_defines::Index,
             HIDE_G: ::enums_arena_defines::Generation,
             #user_bound
         {
             #[allow(unused)]
-            pub fn get(&self, id: #id_ident<HIDE_I, HIDE_G>) -> Option<#name #user_generics> {
-                let (ty, index, g) = id;
-                if g != self.g {
-                    return None;
-                }
-                let ty_index = self.enums_vec_id_offset_of.get(index.to_usize())?.clone();
+            pub fn get(&self, id: #id_ident<HIDE_I, HIDE_G>) -> Option<#name #generics> {
+                let (ty, real_index) = self.slot(id)?;
                 match ty {
                     #(#get_cloned_match_body),*
                 }
             }
+
+            #[allow(unused)]
+            pub fn get_at(&self, index: HIDE_I) -> Option<#name #generics> {
+                self.get(self.id_at(index)?)
+            }
+        }
+
+        impl #impl_generics ::enums_arena_defines::ArenaEnum for #name #ty_generics #where_clause {
+            type Arena<HIDE_I: ::enums_arena_defines::Index, HIDE_G: ::enums_arena_defines::Generation> = #arena_name_ident #arena_ty_generics;
+            type Id<HIDE_I: ::enums_arena_defines::Index, HIDE_G: ::enums_arena_defines::Generation> = #id_ident<HIDE_I, HIDE_G>;
+            type Kind = #enum_name_ident;
         }
 
+        #kind_part
+        #kind_set_part
+        #group_part
+        #visitor_part
+        #handlers_part
+        #map_part
+        #arena_trait_part
+        #journal_part
+        #stable_part
+        #ring_part
+        #typed_part
+        #convert_part
+
+        #serde_part
+
+        #snapshot_part
     };
 
     Ok(res.into())
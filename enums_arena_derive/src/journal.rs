@@ -0,0 +1,290 @@
+use quote::{format_ident, quote};
+
+use crate::id_arena::Context;
+
+/// Generate `{Name}Journal`, a wrapper of the arena which records
+/// the inverse of every mutation so they can be undone and redone.
+pub fn expand(ctx: &Context) -> proc_macro2::TokenStream {
+    let Context {
+        name,
+        vis,
+        generics,
+        new_generics,
+        user_bound,
+        id_ident,
+        enum_name_ident,
+        arena_name_ident,
+        variants,
+        ..
+    } = ctx;
+    let journal_ident = format_ident!("{}Journal", name);
+    let op_ident = format_ident!("{}JournalOp", name);
+
+    let mut kind_match_body = Vec::new();
+    let mut field_fn = Vec::new();
+    let mut modify_fn = Vec::new();
+
+    for variant in variants {
+        let ident = &variant.ident;
+        let alloc_ident = format_ident!("alloc_{}", variant.ident_case);
+        if let Some(ty) = &variant.ty {
+            let get_ident = format_ident!("get_{}", variant.ident_case);
+            let get_mut_ident = format_ident!("get_{}_mut", variant.ident_case);
+            let modify_ident = format_ident!("modify_{}", variant.ident_case);
+            kind_match_body.push(quote! {
+                #name::#ident(_) => #enum_name_ident::#ident
+            });
+            field_fn.push(quote! {
+                #[allow(unused)]
+                pub fn #alloc_ident(&mut self, val: #ty) -> #id_ident<HIDE_I, HIDE_G> {
+                    let id = self.arena.#alloc_ident(val);
+                    self.record(#op_ident::Alloc(id));
+                    id
+                }
+            });
+            modify_fn.push(quote! {
+                /// Mutate the value in place, the value before `f` runs is recorded.
+                #[allow(unused)]
+                pub fn #modify_ident<HIDE_R>(&mut self, id: #id_ident<HIDE_I, HIDE_G>, f: impl FnOnce(&mut #ty) -> HIDE_R) -> Option<HIDE_R> {
+                    let old = self.arena.#get_ident(id)?.clone();
+                    let ret = f(self.arena.#get_mut_ident(id)?);
+                    self.record(#op_ident::Restore(id, #name::#ident(old)));
+                    Some(ret)
+                }
+            });
+        } else {
+            kind_match_body.push(quote! {
+                #name::#ident => #enum_name_ident::#ident
+            });
+            field_fn.push(quote! {
+                #[allow(unused)]
+                pub fn #alloc_ident(&mut self) -> #id_ident<HIDE_I, HIDE_G> {
+                    let id = self.arena.#alloc_ident();
+                    self.record(#op_ident::Alloc(id));
+                    id
+                }
+            });
+        }
+    }
+
+    quote! {
+        enum #op_ident #new_generics {
+            /// Undone by removing the last allocation.
+            Alloc(#id_ident<HIDE_I, HIDE_G>),
+            /// Undone by allocating the value again under the same id.
+            Realloc(#id_ident<HIDE_I, HIDE_G>, #name #generics),
+            /// Undone by replacing the value back.
+            Restore(#id_ident<HIDE_I, HIDE_G>, #name #generics),
+        }
+
+        #[derive(Default)]
+        #vis struct #journal_ident #new_generics {
+            arena: #arena_name_ident #new_generics,
+            undo: ::enums_arena_defines::alloc::vec::Vec<::enums_arena_defines::alloc::vec::Vec<#op_ident #new_generics>>,
+            redo: ::enums_arena_defines::alloc::vec::Vec<::enums_arena_defines::alloc::vec::Vec<#op_ident #new_generics>>,
+            pending: ::enums_arena_defines::alloc::vec::Vec<#op_ident #new_generics>,
+            marks: ::enums_arena_defines::alloc::vec::Vec<usize>,
+        }
+
+        impl #new_generics #arena_name_ident #new_generics
+        where HIDE_I: ::enums_arena_defines::Index,
+            HIDE_G: ::enums_arena_defines::Generation,
+        {
+            /// Allocate the value in the next slot under `id`, which an undone allocation had.
+            fn realloc(&mut self, id: #id_ident<HIDE_I, HIDE_G>, val: #name #generics) -> Option<()> {
+                let (index, g) = Self::id_parts(id);
+                if index.to_usize() != self.len() {
+                    return None;
+                }
+                self.enums_vec_id_g_of.reuse(index.to_usize(), g, self.g);
+                self.alloc(val);
+                Some(())
+            }
+        }
+
+        impl #new_generics ::core::ops::Deref for #journal_ident #new_generics {
+            type Target = #arena_name_ident #new_generics;
+
+            fn deref(&self) -> &Self::Target {
+                &self.arena
+            }
+        }
+
+        impl #new_generics #journal_ident #new_generics
+        where HIDE_I: ::enums_arena_defines::Index,
+            HIDE_G: ::enums_arena_defines::Generation,
+        {
+            #[allow(unused)]
+            pub fn new(arena: #arena_name_ident #new_generics) -> Self {
+                Self {
+                    arena,
+                    undo: ::enums_arena_defines::alloc::vec::Vec::new(),
+                    redo: ::enums_arena_defines::alloc::vec::Vec::new(),
+                    pending: ::enums_arena_defines::alloc::vec::Vec::new(),
+                    marks: ::enums_arena_defines::alloc::vec::Vec::new(),
+                }
+            }
+
+            #[allow(unused)]
+            pub fn into_inner(self) -> #arena_name_ident #new_generics {
+                self.arena
+            }
+
+            fn record(&mut self, op: #op_ident #new_generics) {
+                self.redo.clear();
+                if self.marks.is_empty() {
+                    self.undo.push(::enums_arena_defines::alloc::vec![op]);
+                } else {
+                    self.pending.push(op);
+                }
+            }
+
+            /// Apply the operation, returns the operation which reverts it.
+            fn apply(&mut self, op: #op_ident #new_generics) -> Option<#op_ident #new_generics> {
+                match op {
+                    #op_ident::Alloc(id) => {
+                        let (index, g) = <#arena_name_ident #new_generics>::id_parts(id);
+                        if g != self.arena.generation_of(index.to_usize()) || index.to_usize() + 1 != self.arena.len() {
+                            return None;
+                        }
+                        let val = self.arena.pop()?;
+                        // The id must not find the value allocated next in its slot.
+                        self.arena.next_generation();
+                        Some(#op_ident::Realloc(id, val))
+                    }
+                    #op_ident::Realloc(id, val) => {
+                        self.arena.realloc(id, val)?;
+                        Some(#op_ident::Alloc(id))
+                    }
+                    #op_ident::Restore(id, val) => {
+                        let old = self.arena.replace(id, val)?;
+                        Some(#op_ident::Restore(id, old))
+                    }
+                }
+            }
+
+            /// Revert a group of operations, returns the group which reverts it back.
+            ///
+            /// Only the journal changes its arena, so every recorded operation still applies.
+            fn revert(&mut self, group: ::enums_arena_defines::alloc::vec::Vec<#op_ident #new_generics>) -> ::enums_arena_defines::alloc::vec::Vec<#op_ident #new_generics> {
+                let mut inverse = ::enums_arena_defines::alloc::vec::Vec::with_capacity(group.len());
+                for op in group.into_iter().rev() {
+                    let op = self.apply(op);
+                    ::core::debug_assert!(op.is_some(), "a recorded operation no longer applies to the arena");
+                    inverse.extend(op);
+                }
+                inverse
+            }
+
+            #[allow(unused)]
+            pub fn arena(&self) -> &#arena_name_ident #new_generics {
+                &self.arena
+            }
+
+            /// Clears the arena and the history.
+            #[allow(unused)]
+            pub fn clear(&mut self) {
+                self.arena.clear();
+                self.undo.clear();
+                self.redo.clear();
+                self.pending.clear();
+                self.marks.clear();
+            }
+
+            #[allow(unused)]
+            pub fn alloc(&mut self, val: #name #generics) -> #id_ident<HIDE_I, HIDE_G> {
+                let id = self.arena.alloc(val);
+                self.record(#op_ident::Alloc(id));
+                id
+            }
+
+            #[allow(unused)]
+            pub fn update(&mut self, id: #id_ident<HIDE_I, HIDE_G>, val: #name #generics) -> Option<()> {
+                let ty = match &val {
+                    #(#kind_match_body),*
+                };
+                if self.arena.slot(id)?.0 != ty {
+                    return None;
+                }
+                let op = self.apply(#op_ident::Restore(id, val))?;
+                self.record(op);
+                Some(())
+            }
+
+            /// Start a transaction, all changes until the matching
+            /// [`commit`](Self::commit) are undone as one step.
+            ///
+            /// Transactions can be nested.
+            #[allow(unused)]
+            pub fn begin(&mut self) {
+                self.marks.push(self.pending.len());
+            }
+
+            /// Finish the innermost transaction.
+            ///
+            /// Returns `None` if no transaction is open.
+            #[allow(unused)]
+            pub fn commit(&mut self) -> Option<()> {
+                self.marks.pop()?;
+                if self.marks.is_empty() && !self.pending.is_empty() {
+                    let group = ::core::mem::take(&mut self.pending);
+                    self.undo.push(group);
+                }
+                Some(())
+            }
+
+            /// Revert all changes of the innermost transaction.
+            ///
+            /// Returns `None` if no transaction is open.
+            #[allow(unused)]
+            pub fn abort(&mut self) -> Option<()> {
+                let mark = self.marks.pop()?;
+                let group = self.pending.split_off(mark);
+                self.revert(group);
+                Some(())
+            }
+
+            /// Returns `None` if there is nothing to undo or a transaction is open.
+            #[allow(unused)]
+            pub fn undo(&mut self) -> Option<()> {
+                if !self.marks.is_empty() {
+                    return None;
+                }
+                let group = self.undo.pop()?;
+                let group = self.revert(group);
+                self.redo.push(group);
+                Some(())
+            }
+
+            /// Returns `None` if there is nothing to redo or a transaction is open.
+            #[allow(unused)]
+            pub fn redo(&mut self) -> Option<()> {
+                if !self.marks.is_empty() {
+                    return None;
+                }
+                let group = self.redo.pop()?;
+                let group = self.revert(group);
+                self.undo.push(group);
+                Some(())
+            }
+
+            #(#field_fn)*
+        }
+
+        impl #new_generics #journal_ident #new_generics
+        where HIDE_I: ::enums_arena_defines::Index,
+            HIDE_G: ::enums_arena_defines::Generation,
+            #user_bound
+        {
+            #[allow(unused)]
+            pub fn replace(&mut self, id: #id_ident<HIDE_I, HIDE_G>, val: #name #generics) -> Option<#name #generics> {
+                let old = self.arena.get(id)?;
+                let op = self.apply(#op_ident::Restore(id, val))?;
+                self.record(op);
+                Some(old)
+            }
+
+            #(#modify_fn)*
+        }
+    }
+}
@@ -0,0 +1,123 @@
+use proc_macro2::Literal;
+use quote::quote;
+
+use crate::id_arena::Context;
+
+/// Generate the helpers of `{Name}ExtendEnum`: `ALL`, `COUNT`, `name`,
+/// `FromStr`, `TryFrom<u8>` and `TryFrom<u16>`, and `kind` of the user enum.
+pub fn expand(ctx: &Context) -> proc_macro2::TokenStream {
+    let Context {
+        name,
+        generics,
+        enum_name_ident,
+        variants,
+        ..
+    } = ctx;
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+
+    let count = variants.len();
+    let mut all = Vec::new();
+    let mut name_match_body = Vec::new();
+    let mut from_str_match_body = Vec::new();
+    let mut from_tag_match_body = Vec::new();
+    let mut kind_match_body = Vec::new();
+
+    for (i, variant) in variants.iter().enumerate() {
+        let ident = &variant.ident;
+        let ident_str = ident.to_string();
+        let tag = Literal::usize_unsuffixed(i);
+        all.push(quote! {
+            #enum_name_ident::#ident
+        });
+        name_match_body.push(quote! {
+            #enum_name_ident::#ident => #ident_str
+        });
+        from_str_match_body.push(quote! {
+            #ident_str => Ok(#enum_name_ident::#ident)
+        });
+        from_tag_match_body.push(quote! {
+            #tag => Ok(#enum_name_ident::#ident)
+        });
+        kind_match_body.push(match &variant.ty {
+            Some(_) => quote! {
+                #name::#ident(_) => #enum_name_ident::#ident
+            },
+            None => quote! {
+                #name::#ident => #enum_name_ident::#ident
+            },
+        });
+    }
+
+    // Tags above `u8::MAX` can't be given as a `u8`.
+    let from_u8_match_body: Vec<_> = from_tag_match_body
+        .iter()
+        .take(u8::MAX as usize + 1)
+        .collect();
+
+    quote! {
+        impl #enum_name_ident {
+            /// All kinds in declaration order.
+            #[allow(unused)]
+            pub const ALL: [Self; #count] = [#(#all),*];
+
+            /// Number of variants.
+            #[allow(unused)]
+            pub const COUNT: usize = #count;
+
+            /// Name of the variant.
+            #[allow(unused)]
+            pub fn name(self) -> &'static str {
+                match self {
+                    #(#name_match_body),*
+                }
+            }
+        }
+
+        impl ::core::str::FromStr for #enum_name_ident {
+            type Err = ::enums_arena_defines::UnknownKind;
+
+            /// Parse the name of a variant.
+            fn from_str(s: &str) -> ::core::result::Result<Self, Self::Err> {
+                match s {
+                    #(#from_str_match_body,)*
+                    _ => Err(::enums_arena_defines::UnknownKind),
+                }
+            }
+        }
+
+        impl ::core::convert::TryFrom<u8> for #enum_name_ident {
+            type Error = ::enums_arena_defines::UnknownKind;
+
+            /// The kind of the variant declared at `tag`.
+            fn try_from(tag: u8) -> ::core::result::Result<Self, Self::Error> {
+                #[allow(unreachable_patterns)]
+                match tag {
+                    #(#from_u8_match_body,)*
+                    _ => Err(::enums_arena_defines::UnknownKind),
+                }
+            }
+        }
+
+        impl ::core::convert::TryFrom<u16> for #enum_name_ident {
+            type Error = ::enums_arena_defines::UnknownKind;
+
+            /// The kind of the variant declared at `tag`.
+            fn try_from(tag: u16) -> ::core::result::Result<Self, Self::Error> {
+                match tag {
+                    #(#from_tag_match_body,)*
+                    _ => Err(::enums_arena_defines::UnknownKind),
+                }
+            }
+        }
+
+        impl #impl_generics #name #ty_generics #where_clause {
+            /// Kind of the value, as stored in the arena.
+            #[allow(unused)]
+            pub fn kind(&self) -> #enum_name_ident {
+                match self {
+                    #(#kind_match_body),*
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,253 @@
+use proc_macro2::Literal;
+use quote::{format_ident, quote};
+
+use crate::id_arena::Context;
+
+/// Generate `{Name}KindSet`, a bitmask over `{Name}ExtendEnum`, and
+/// `iter_kinds` and `count_kinds` of the arena.
+pub fn expand(ctx: &Context) -> proc_macro2::TokenStream {
+    let Context {
+        name,
+        vis,
+        new_generics,
+        id_ident,
+        enum_name_ident,
+        arena_name_ident,
+        variants,
+        ..
+    } = ctx;
+    let set_ident = format_ident!("{}KindSet", name);
+
+    let words = variants.len().div_ceil(64).max(1);
+    let all_words = (0..words).map(|i| {
+        let bits = match variants.len() - (i * 64).min(variants.len()) {
+            n if n >= 64 => u64::MAX,
+            n => (1u64 << n) - 1,
+        };
+        Literal::u64_suffixed(bits)
+    });
+
+    let mut count_data = Vec::new();
+    let mut unit_kinds = Vec::new();
+    for variant in variants {
+        let ident = &variant.ident;
+        match &variant.ty {
+            Some(_) => {
+                let vec_ident = format_ident!("{}_vec", variant.ident_case);
+                count_data.push(quote! {
+                    if set.contains(#enum_name_ident::#ident) {
+                        count += ::enums_arena_defines::Storage::len(&self.#vec_ident);
+                    }
+                });
+            }
+            None => unit_kinds.push(quote! {
+                .with(#enum_name_ident::#ident)
+            }),
+        }
+    }
+
+    quote! {
+        /// Set of kinds, one bit per variant.
+        #[derive(Clone, Copy, PartialEq, Eq, Hash)]
+        #vis struct #set_ident {
+            words: [u64; #words],
+        }
+
+        impl #set_ident {
+            #[allow(unused)]
+            pub const EMPTY: Self = Self { words: [0; #words] };
+
+            #[allow(unused)]
+            pub const ALL: Self = Self { words: [#(#all_words),*] };
+
+            const fn bit(kind: #enum_name_ident) -> (usize, u64) {
+                let i = kind as usize;
+                (i / 64, 1 << (i % 64))
+            }
+
+            #[allow(unused)]
+            pub const fn only(kind: #enum_name_ident) -> Self {
+                Self::EMPTY.with(kind)
+            }
+
+            #[allow(unused)]
+            pub const fn with(mut self, kind: #enum_name_ident) -> Self {
+                let (word, bit) = Self::bit(kind);
+                self.words[word] |= bit;
+                self
+            }
+
+            #[allow(unused)]
+            pub const fn without(mut self, kind: #enum_name_ident) -> Self {
+                let (word, bit) = Self::bit(kind);
+                self.words[word] &= !bit;
+                self
+            }
+
+            #[allow(unused)]
+            pub const fn contains(self, kind: #enum_name_ident) -> bool {
+                let (word, bit) = Self::bit(kind);
+                self.words[word] & bit != 0
+            }
+
+            #[allow(unused)]
+            pub const fn union(mut self, other: Self) -> Self {
+                let mut i = 0;
+                while i < #words {
+                    self.words[i] |= other.words[i];
+                    i += 1;
+                }
+                self
+            }
+
+            #[allow(unused)]
+            pub const fn intersection(mut self, other: Self) -> Self {
+                let mut i = 0;
+                while i < #words {
+                    self.words[i] &= other.words[i];
+                    i += 1;
+                }
+                self
+            }
+
+            #[allow(unused)]
+            pub const fn difference(mut self, other: Self) -> Self {
+                let mut i = 0;
+                while i < #words {
+                    self.words[i] &= !other.words[i];
+                    i += 1;
+                }
+                self
+            }
+
+            #[allow(unused)]
+            pub const fn complement(self) -> Self {
+                Self::ALL.difference(self)
+            }
+
+            /// Number of kinds in the set.
+            #[allow(unused)]
+            pub const fn len(self) -> usize {
+                let mut len = 0;
+                let mut i = 0;
+                while i < #words {
+                    len += self.words[i].count_ones() as usize;
+                    i += 1;
+                }
+                len
+            }
+
+            #[allow(unused)]
+            pub const fn is_empty(self) -> bool {
+                self.len() == 0
+            }
+
+            /// Returns whether the kind was not in the set.
+            #[allow(unused)]
+            pub fn insert(&mut self, kind: #enum_name_ident) -> bool {
+                let added = !self.contains(kind);
+                *self = self.with(kind);
+                added
+            }
+
+            /// Returns whether the kind was in the set.
+            #[allow(unused)]
+            pub fn remove(&mut self, kind: #enum_name_ident) -> bool {
+                let removed = self.contains(kind);
+                *self = self.without(kind);
+                removed
+            }
+
+            /// Kinds in declaration order.
+            #[allow(unused)]
+            pub fn iter(self) -> impl Iterator<Item = #enum_name_ident> {
+                #enum_name_ident::ALL.into_iter().filter(move |kind| self.contains(*kind))
+            }
+        }
+
+        impl ::core::default::Default for #set_ident {
+            fn default() -> Self {
+                Self::EMPTY
+            }
+        }
+
+        impl ::core::fmt::Debug for #set_ident {
+            fn fmt(&self, f: &mut ::core::fmt::Formatter<'_>) -> ::core::fmt::Result {
+                f.debug_set().entries(self.iter()).finish()
+            }
+        }
+
+        impl ::core::convert::From<#enum_name_ident> for #set_ident {
+            fn from(kind: #enum_name_ident) -> Self {
+                Self::only(kind)
+            }
+        }
+
+        impl ::core::iter::FromIterator<#enum_name_ident> for #set_ident {
+            fn from_iter<T: IntoIterator<Item = #enum_name_ident>>(iter: T) -> Self {
+                iter.into_iter().fold(Self::EMPTY, Self::with)
+            }
+        }
+
+        impl ::core::ops::BitOr for #set_ident {
+            type Output = Self;
+
+            fn bitor(self, other: Self) -> Self {
+                self.union(other)
+            }
+        }
+
+        impl ::core::ops::BitAnd for #set_ident {
+            type Output = Self;
+
+            fn bitand(self, other: Self) -> Self {
+                self.intersection(other)
+            }
+        }
+
+        impl ::core::ops::Sub for #set_ident {
+            type Output = Self;
+
+            fn sub(self, other: Self) -> Self {
+                self.difference(other)
+            }
+        }
+
+        impl ::core::ops::Not for #set_ident {
+            type Output = Self;
+
+            fn not(self) -> Self {
+                self.complement()
+            }
+        }
+
+        impl #new_generics #arena_name_ident #new_generics
+        where HIDE_I: ::enums_arena_defines::Index,
+            HIDE_G: ::enums_arena_defines::Generation,
+        {
+            /// Ids of the values whose kind is in `set`, in allocation order.
+            #[allow(unused)]
+            pub fn iter_kinds(&self, set: #set_ident) -> impl Iterator<Item = #id_ident<HIDE_I, HIDE_G>> + '_ {
+                self.enums_vec_id_kind_of
+                    .iter()
+                    .enumerate()
+                    .filter(move |(_, ty)| set.contains(**ty))
+                    .map(|(index, ty)| self.new_id(*ty, HIDE_I::from_usize(index)))
+            }
+
+            /// Number of values whose kind is in `set`.
+            ///
+            /// Unit variants have no storage, they are only counted by scanning the kinds.
+            #[allow(unused)]
+            pub fn count_kinds(&self, set: #set_ident) -> usize {
+                let mut count = 0;
+                #(#count_data)*
+                let units = set.intersection(#set_ident::EMPTY #(#unit_kinds)*);
+                if !units.is_empty() {
+                    count += self.enums_vec_id_kind_of.iter().filter(|ty| units.contains(**ty)).count();
+                }
+                count
+            }
+        }
+    }
+}
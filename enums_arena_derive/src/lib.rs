@@ -1,9 +1,24 @@
 use proc_macro::TokenStream;
 use syn::{parse_macro_input, DeriveInput};
 
+mod arena_trait;
+mod attr;
+mod convert;
+mod group;
+mod handlers;
 mod id_arena;
+mod journal;
+mod kind;
+mod kind_set;
+mod map;
+mod ring;
+mod serde_impl;
+mod snapshot;
+mod stable;
+mod typed;
+mod visitor;
 
-#[proc_macro_derive(EnumsIdArena)]
+#[proc_macro_derive(EnumsIdArena, attributes(enums_arena, arena))]
 pub fn enums_id_arena(input: TokenStream) -> TokenStream {
     let ast = parse_macro_input!(input as DeriveInput);
     id_arena::enums_id_arena_to(&ast).unwrap_or_else(|err| err.to_compile_error().into())
@@ -0,0 +1,259 @@
+use proc_macro::Span;
+use quote::{format_ident, quote};
+use syn::{parse_quote, GenericParam, Lifetime, LifetimeParam};
+
+use crate::id_arena::Context;
+
+/// Generate `{Name}Ref`, a borrowed value of the arena, `get_ref`, `iter`
+/// and `map_into` which copies the arena into another one with `{Name}IdRemap`.
+///
+/// The arena implements `AllocArena`.
+pub fn expand(ctx: &Context) -> proc_macro2::TokenStream {
+    let Context {
+        name,
+        vis,
+        generics,
+        new_generics,
+        id_ident,
+        enum_name_ident,
+        arena_name_ident,
+        variants,
+        options,
+        ..
+    } = ctx;
+    let ref_ident = format_ident!("{}Ref", name);
+    let remap_ident = format_ident!("{}IdRemap", name);
+
+    let mut ref_generics = (*generics).clone();
+    ref_generics.params.insert(
+        0,
+        GenericParam::Lifetime(LifetimeParam::new(Lifetime::new(
+            "'__enums_arena_r",
+            Span::call_site().into(),
+        ))),
+    );
+    let (ref_impl_generics, ref_ty_generics, _) = ref_generics.split_for_impl();
+    let ref_ty = ref_type(ctx, quote!('_));
+    let ref_ty_r = ref_type(ctx, quote!('__enums_arena_r));
+
+    let mut ref_variants = Vec::new();
+    let mut get_ref_match_body = Vec::new();
+    let mut debug_match_body = Vec::new();
+    let mut eq_match_body = Vec::new();
+    // `&'r T: Debug` rather than `T: Debug`, so the bounds are never trivially false
+    // and `Debug` and `PartialEq` are only implemented when the payloads have them.
+    let mut debug_generics = ref_generics.clone();
+    let mut eq_generics = ref_generics.clone();
+
+    for variant in variants {
+        let ident = &variant.ident;
+        let Some(ty) = &variant.ty else {
+            let ident_str = ident.to_string();
+            ref_variants.push(quote! {
+                #ident
+            });
+            get_ref_match_body.push(quote! {
+                #enum_name_ident::#ident => Some(#ref_ident::#ident)
+            });
+            debug_match_body.push(quote! {
+                #ref_ident::#ident => f.write_str(#ident_str)
+            });
+            eq_match_body.push(quote! {
+                (#ref_ident::#ident, #ref_ident::#ident) => true
+            });
+            continue;
+        };
+        let ident_str = ident.to_string();
+        let vec_ident = format_ident!("{}_vec", variant.ident_case);
+        ref_variants.push(quote! {
+            #ident(&'__enums_arena_r #ty)
+        });
+        debug_match_body.push(quote! {
+            #ref_ident::#ident(val) => f.debug_tuple(#ident_str).field(val).finish()
+        });
+        eq_match_body.push(quote! {
+            (#ref_ident::#ident(a), #ref_ident::#ident(b)) => a == b
+        });
+        debug_generics
+            .make_where_clause()
+            .predicates
+            .push(parse_quote!(&'__enums_arena_r #ty: ::core::fmt::Debug));
+        eq_generics
+            .make_where_clause()
+            .predicates
+            .push(parse_quote!(&'__enums_arena_r #ty: ::core::cmp::PartialEq));
+        get_ref_match_body.push(quote! {
+            #enum_name_ident::#ident => {
+                ::enums_arena_defines::Storage::get(&self.#vec_ident, real_index).map(#ref_ident::#ident)
+            }
+        });
+    }
+
+    if variants.iter().all(|variant| variant.ty.is_none()) {
+        // Only unit variants, the lifetime has to be used.
+        ref_variants.push(quote! {
+            #[doc(hidden)]
+            __Borrow(::core::marker::PhantomData<&'__enums_arena_r ()>, ::core::convert::Infallible)
+        });
+        debug_match_body.push(quote! {
+            #ref_ident::__Borrow(_, never) => match *never {}
+        });
+    }
+    let (_, _, debug_where_clause) = debug_generics.split_for_impl();
+    let (_, _, eq_where_clause) = eq_generics.split_for_impl();
+
+    let remap_id_parts = if options.compact_id {
+        quote! {
+            let (index, g) = id;
+        }
+    } else {
+        quote! {
+            let (_, index, g) = id;
+        }
+    };
+
+    quote! {
+        /// A value of the arena borrowed in place.
+        #vis enum #ref_ident #ref_generics {
+            #(#ref_variants),*
+        }
+
+        impl #ref_impl_generics ::core::fmt::Debug for #ref_ident #ref_ty_generics #debug_where_clause {
+            fn fmt(&self, f: &mut ::core::fmt::Formatter<'_>) -> ::core::fmt::Result {
+                match self {
+                    #(#debug_match_body),*
+                }
+            }
+        }
+
+        impl #ref_impl_generics ::core::cmp::PartialEq for #ref_ident #ref_ty_generics #eq_where_clause {
+            fn eq(&self, other: &Self) -> bool {
+                #[allow(unreachable_patterns)]
+                match (self, other) {
+                    #(#eq_match_body,)*
+                    _ => false,
+                }
+            }
+        }
+
+        impl #ref_impl_generics Clone for #ref_ident #ref_ty_generics {
+            fn clone(&self) -> Self {
+                *self
+            }
+        }
+
+        impl #ref_impl_generics Copy for #ref_ident #ref_ty_generics {}
+
+        /// Ids of a source arena mapped to the ids of the arena it is copied into.
+        #vis struct #remap_ident<HIDE_I, HIDE_G, T> {
+            g: HIDE_G,
+            generations: ::enums_arena_defines::Generations<HIDE_G>,
+            ids: ::enums_arena_defines::alloc::vec::Vec<T>,
+            index: ::core::marker::PhantomData<HIDE_I>,
+        }
+
+        impl<HIDE_I, HIDE_G, T> #remap_ident<HIDE_I, HIDE_G, T>
+        where HIDE_I: ::enums_arena_defines::Index,
+            HIDE_G: ::enums_arena_defines::Generation,
+            T: Copy,
+        {
+            /// The new id of `id`, `None` if `id` is not from the source arena.
+            #[allow(unused)]
+            pub fn get(&self, id: #id_ident<HIDE_I, HIDE_G>) -> Option<T> {
+                #remap_id_parts
+                let index = index.to_usize();
+                if g != self.generations.get(index, self.g) {
+                    return None;
+                }
+                self.ids.get(index).copied()
+            }
+
+            #[allow(unused)]
+            pub fn len(&self) -> usize {
+                self.ids.len()
+            }
+
+            #[allow(unused)]
+            pub fn is_empty(&self) -> bool {
+                self.ids.is_empty()
+            }
+        }
+
+        impl #new_generics #arena_name_ident #new_generics
+        where HIDE_I: ::enums_arena_defines::Index,
+            HIDE_G: ::enums_arena_defines::Generation,
+        {
+            #[allow(unused)]
+            pub fn get_ref(&self, id: #id_ident<HIDE_I, HIDE_G>) -> Option<#ref_ty> {
+                let (ty, real_index) = self.slot(id)?;
+                match ty {
+                    #(#get_ref_match_body),*
+                }
+            }
+
+            /// Values in allocation order.
+            #[allow(unused)]
+            pub fn iter(&self) -> impl Iterator<Item = (#id_ident<HIDE_I, HIDE_G>, #ref_ty)> + '_ {
+                (0..self.len()).filter_map(move |index| {
+                    let id = self.id_at(HIDE_I::from_usize(index))?;
+                    Some((id, self.get_ref(id)?))
+                })
+            }
+
+            /// Copy every value through `f` into a new arena, in allocation order.
+            ///
+            /// Returns the new arena and the new id of each id, ids in the
+            /// new values can be rewritten with it.
+            #[allow(unused)]
+            pub fn map_into<'__enums_arena_r, A: ::enums_arena_defines::AllocArena + Default>(
+                &'__enums_arena_r self,
+                mut f: impl FnMut(#id_ident<HIDE_I, HIDE_G>, #ref_ty_r) -> A::Value,
+            ) -> (A, #remap_ident<HIDE_I, HIDE_G, A::Id>) {
+                let mut arena = A::default();
+                let mut ids = ::enums_arena_defines::alloc::vec::Vec::with_capacity(self.len());
+                for (id, val) in self.iter() {
+                    ids.push(arena.alloc(f(id, val)));
+                }
+                let remap = #remap_ident {
+                    g: self.g,
+                    generations: self.enums_vec_id_g_of.clone(),
+                    ids,
+                    index: ::core::marker::PhantomData,
+                };
+                (arena, remap)
+            }
+        }
+
+        impl #new_generics ::enums_arena_defines::AllocArena for #arena_name_ident #new_generics
+        where HIDE_I: ::enums_arena_defines::Index,
+            HIDE_G: ::enums_arena_defines::Generation,
+        {
+            type Id = #id_ident<HIDE_I, HIDE_G>;
+            type Value = #name #generics;
+
+            fn alloc(&mut self, val: Self::Value) -> Self::Id {
+                self.alloc(val)
+            }
+        }
+    }
+}
+
+/// `{Name}Ref` borrowing for `lifetime`.
+pub fn ref_type(ctx: &Context, lifetime: proc_macro2::TokenStream) -> proc_macro2::TokenStream {
+    let ref_ident = format_ident!("{}Ref", ctx.name);
+    let user_args = ctx.generics.params.iter().map(|param| match param {
+        GenericParam::Lifetime(param) => {
+            let lifetime = &param.lifetime;
+            quote!(#lifetime)
+        }
+        GenericParam::Type(param) => {
+            let ident = &param.ident;
+            quote!(#ident)
+        }
+        GenericParam::Const(param) => {
+            let ident = &param.ident;
+            quote!(#ident)
+        }
+    });
+    quote!(#ref_ident<#lifetime, #(#user_args),*>)
+}
@@ -0,0 +1,258 @@
+use quote::{format_ident, quote};
+
+use crate::id_arena::Context;
+
+/// Generate `{Name}RingArena`, a bounded arena which overwrites the oldest value
+/// of a variant once the variant is full.
+///
+/// Ids are `(kind, slot, sequence number)`, a slot is reused by a later value with
+/// another sequence number, so an id of an overwritten value finds nothing.
+pub fn expand(ctx: &Context) -> proc_macro2::TokenStream {
+    let Context {
+        name,
+        vis,
+        generics,
+        new_generics,
+        user_bound,
+        enum_name_ident,
+        variants,
+        ..
+    } = ctx;
+    let ring_ident = format_ident!("{}RingArena", name);
+    let ring_id_ident = format_ident!("{}RingId", name);
+
+    // The generation is replaced by the sequence number.
+    let mut ring_generics = new_generics.clone();
+    ring_generics.params.pop();
+
+    let mut ring_fields = Vec::new();
+    let mut ring_new = Vec::new();
+    let mut clear_rings = Vec::new();
+    let mut len_rings = Vec::new();
+    let mut alloc_match_body = Vec::new();
+    let mut kind_match_body = Vec::new();
+    let mut get_match_body = Vec::new();
+    let mut field_fn = Vec::new();
+
+    for variant in variants {
+        let ident = &variant.ident;
+        let ring_vec_ident = format_ident!("{}_ring", variant.ident_case);
+        let next_ident = format_ident!("{}_next", variant.ident_case);
+        let alloc_ident = format_ident!("alloc_{}", variant.ident_case);
+
+        ring_new.push(quote! {
+            #ring_vec_ident: ::enums_arena_defines::alloc::vec::Vec::new(),
+            #next_ident: 0
+        });
+        clear_rings.push(quote! {
+            self.#ring_vec_ident.clear();
+            self.#next_ident = 0
+        });
+        len_rings.push(quote! {
+            self.#ring_vec_ident.len()
+        });
+
+        let Some(ty) = &variant.ty else {
+            ring_fields.push(quote! {
+                #ring_vec_ident: ::enums_arena_defines::alloc::vec::Vec<u64>,
+                #next_ident: usize
+            });
+            alloc_match_body.push(quote! {
+                #name::#ident => self.#alloc_ident()
+            });
+            kind_match_body.push(quote! {
+                #enum_name_ident::#ident => self.#ring_vec_ident.get(slot) == Some(&seq)
+            });
+            get_match_body.push(quote! {
+                #enum_name_ident::#ident => Some(#name::#ident)
+            });
+            field_fn.push(quote! {
+                #[allow(unused)]
+                pub fn #alloc_ident(&mut self) -> #ring_id_ident<HIDE_I> {
+                    let seq = self.next_seq();
+                    let slot = Self::next_slot(&mut self.#next_ident, self.#ring_vec_ident.len(), self.capacity);
+                    match self.#ring_vec_ident.get_mut(slot) {
+                        Some(old) => *old = seq,
+                        None => self.#ring_vec_ident.push(seq),
+                    }
+                    (#enum_name_ident::#ident, HIDE_I::from_usize(slot), seq)
+                }
+            });
+            continue;
+        };
+        let get_ident = format_ident!("get_{}", variant.ident_case);
+        let get_mut_ident = format_ident!("get_{}_mut", variant.ident_case);
+        let iter_ident = format_ident!("iter_{}", variant.ident_case);
+
+        ring_fields.push(quote! {
+            #ring_vec_ident: ::enums_arena_defines::alloc::vec::Vec<(u64, #ty)>,
+            #next_ident: usize
+        });
+        alloc_match_body.push(quote! {
+            #name::#ident(val) => self.#alloc_ident(val)
+        });
+        kind_match_body.push(quote! {
+            #enum_name_ident::#ident => ::core::matches!(self.#ring_vec_ident.get(slot), Some((s, _)) if *s == seq)
+        });
+        get_match_body.push(quote! {
+            #enum_name_ident::#ident => self.#get_ident(id).cloned().map(#name::#ident)
+        });
+        field_fn.push(quote! {
+            #[allow(unused)]
+            pub fn #alloc_ident(&mut self, val: #ty) -> #ring_id_ident<HIDE_I> {
+                let seq = self.next_seq();
+                let slot = Self::next_slot(&mut self.#next_ident, self.#ring_vec_ident.len(), self.capacity);
+                match self.#ring_vec_ident.get_mut(slot) {
+                    Some(old) => *old = (seq, val),
+                    None => self.#ring_vec_ident.push((seq, val)),
+                }
+                (#enum_name_ident::#ident, HIDE_I::from_usize(slot), seq)
+            }
+
+            #[allow(unused)]
+            pub fn #get_ident(&self, id: #ring_id_ident<HIDE_I>) -> Option<&#ty> {
+                let (ty, slot, seq) = id;
+                if ty != #enum_name_ident::#ident {
+                    return None;
+                }
+                match self.#ring_vec_ident.get(slot.to_usize())? {
+                    (s, val) if *s == seq => Some(val),
+                    _ => None,
+                }
+            }
+
+            #[allow(unused)]
+            pub fn #get_mut_ident(&mut self, id: #ring_id_ident<HIDE_I>) -> Option<&mut #ty> {
+                let (ty, slot, seq) = id;
+                if ty != #enum_name_ident::#ident {
+                    return None;
+                }
+                match self.#ring_vec_ident.get_mut(slot.to_usize())? {
+                    (s, val) if *s == seq => Some(val),
+                    _ => None,
+                }
+            }
+
+            /// Values of the variant from the oldest to the newest.
+            #[allow(unused)]
+            pub fn #iter_ident(&self) -> impl Iterator<Item = (#ring_id_ident<HIDE_I>, &#ty)> + '_ {
+                let next = self.#next_ident;
+                (next..self.#ring_vec_ident.len()).chain(0..next).map(move |slot| {
+                    let (seq, val) = &self.#ring_vec_ident[slot];
+                    ((#enum_name_ident::#ident, HIDE_I::from_usize(slot), *seq), val)
+                })
+            }
+        });
+    }
+
+    quote! {
+        #vis type #ring_id_ident<I> = (#enum_name_ident, I, u64);
+
+        #vis struct #ring_ident #ring_generics {
+            seq: u64,
+            capacity: usize,
+            index: ::core::marker::PhantomData<HIDE_I>,
+            #(#ring_fields),*
+        }
+
+        impl #ring_generics #ring_ident #ring_generics
+        where HIDE_I: ::enums_arena_defines::Index,
+        {
+            /// Every variant keeps at most `capacity` values.
+            ///
+            /// # Panics
+            ///
+            /// Panics if `capacity` is zero or the last slot doesn't fit the index type.
+            #[allow(unused)]
+            pub fn new(capacity: usize) -> Self {
+                ::core::assert!(capacity > 0, "capacity of a ring arena must not be zero");
+                ::core::assert!(
+                    HIDE_I::from_usize(capacity - 1).to_usize() == capacity - 1,
+                    "capacity of a ring arena must fit the index type"
+                );
+                Self {
+                    seq: 0,
+                    capacity,
+                    index: ::core::marker::PhantomData,
+                    #(#ring_new),*
+                }
+            }
+
+            fn next_seq(&mut self) -> u64 {
+                let seq = self.seq;
+                self.seq += 1;
+                seq
+            }
+
+            /// Returns the slot to write and advances `next`.
+            fn next_slot(next: &mut usize, len: usize, capacity: usize) -> usize {
+                let slot = *next;
+                ::core::debug_assert!(slot <= len);
+                *next = (slot + 1) % capacity;
+                slot
+            }
+
+            /// Capacity of each variant.
+            #[allow(unused)]
+            pub fn capacity(&self) -> usize {
+                self.capacity
+            }
+
+            /// Number of values allocated since the arena was created.
+            #[allow(unused)]
+            pub fn seq(&self) -> u64 {
+                self.seq
+            }
+
+            /// Number of values kept.
+            #[allow(unused)]
+            pub fn len(&self) -> usize {
+                0 #(+ #len_rings)*
+            }
+
+            #[allow(unused)]
+            pub fn is_empty(&self) -> bool {
+                self.len() == 0
+            }
+
+            /// The sequence number keeps counting, ids of the cleared values find nothing.
+            #[allow(unused)]
+            pub fn clear(&mut self) {
+                #(#clear_rings;)*
+            }
+
+            /// Returns `None` if the value is overwritten.
+            #[allow(unused)]
+            pub fn kind_of(&self, id: #ring_id_ident<HIDE_I>) -> Option<#enum_name_ident> {
+                let (ty, slot, seq) = id;
+                let slot = slot.to_usize();
+                let found = match ty {
+                    #(#kind_match_body),*
+                };
+                found.then_some(ty)
+            }
+
+            #[allow(unused)]
+            pub fn alloc(&mut self, val: #name #generics) -> #ring_id_ident<HIDE_I> {
+                match val {
+                    #(#alloc_match_body),*
+                }
+            }
+
+            #(#field_fn)*
+        }
+
+        impl #ring_generics #ring_ident #ring_generics
+        where HIDE_I: ::enums_arena_defines::Index,
+            #user_bound
+        {
+            /// Returns `None` if the value is overwritten.
+            #[allow(unused)]
+            pub fn get(&self, id: #ring_id_ident<HIDE_I>) -> Option<#name #generics> {
+                match self.kind_of(id)? {
+                    #(#get_match_body),*
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,244 @@
+use proc_macro::Span;
+use quote::{format_ident, quote};
+use syn::{GenericParam, Lifetime, LifetimeParam};
+
+use crate::id_arena::Context;
+
+/// Attributes of `{Name}ExtendEnum`.
+pub fn kind_attrs() -> proc_macro2::TokenStream {
+    quote! {
+        #[derive(::enums_arena_defines::serde::Serialize, ::enums_arena_defines::serde::Deserialize)]
+        #[serde(crate = "::enums_arena_defines::serde")]
+    }
+}
+
+/// Implement `Serialize` and `Deserialize` for `{Name}IdArena`.
+///
+/// The arena is stored as its generation, the index table, the names of the variants and
+/// the values of each variant, the back references are rebuilt and checked when it is loaded.
+/// Variants are matched by name, like the variants of a snapshot.
+pub fn expand(ctx: &Context) -> proc_macro2::TokenStream {
+    let Context {
+        generics,
+        new_generics,
+        enum_name_ident,
+        repr_ty,
+        arena_name_ident,
+        variants,
+        ..
+    } = ctx;
+    let arena_name = arena_name_ident.to_string();
+
+    let mut ref_generics = new_generics.clone();
+    ref_generics.params.insert(
+        0,
+        GenericParam::Lifetime(LifetimeParam::new(Lifetime::new(
+            "'__enums_arena_ser",
+            Span::call_site().into(),
+        ))),
+    );
+    let params = &new_generics.params;
+
+    let user_types: Vec<_> = generics.type_params().map(|t| &t.ident).collect();
+
+    let mut ref_fields = Vec::new();
+    let mut owned_fields = Vec::new();
+    let mut ref_take = Vec::new();
+    let mut field_names = Vec::new();
+    let mut variant_names = Vec::new();
+    let mut id_of_defines = Vec::new();
+    let mut id_of_match_body = Vec::new();
+    let mut arena_fields = Vec::new();
+
+    for variant in variants {
+        let ident = &variant.ident;
+        variant_names.push(ident.to_string());
+        let Some(ty) = &variant.ty else {
+            id_of_match_body.push(quote! {
+                #enum_name_ident::#ident => {
+                    if real_index.to_usize() != 0 {
+                        return Err(<HIDE_D::Error as ::enums_arena_defines::serde::de::Error>::custom("invalid offset of a unit variant"));
+                    }
+                }
+            });
+            continue;
+        };
+        let field_ident = format_ident!("{}", variant.ident_case);
+        let vec_ident = format_ident!("{}_vec", variant.ident_case);
+        let vec_id_ident = format_ident!("{}_vec_id_of", variant.ident_case);
+        ref_fields.push(quote! {
+            #field_ident: &'__enums_arena_ser [#ty]
+        });
+        owned_fields.push(quote! {
+            #[serde(default = "::enums_arena_defines::alloc::vec::Vec::new")]
+            #field_ident: ::enums_arena_defines::alloc::vec::Vec<#ty>
+        });
+        ref_take.push(quote! {
+            #field_ident: &self.#vec_ident
+        });
+        field_names.push(field_ident.clone());
+        id_of_defines.push(quote! {
+            let mut #vec_id_ident: ::enums_arena_defines::alloc::vec::Vec<Option<HIDE_I>> = ::enums_arena_defines::alloc::vec![None; #field_ident.len()];
+        });
+        id_of_match_body.push(quote! {
+            #enum_name_ident::#ident => match #vec_id_ident.get_mut(real_index.to_usize()) {
+                Some(id_of @ None) => *id_of = Some(HIDE_I::from_usize(offsets.len())),
+                _ => return Err(<HIDE_D::Error as ::enums_arena_defines::serde::de::Error>::custom("invalid offset")),
+            }
+        });
+        arena_fields.push(quote! {
+            #vec_ident: #field_ident,
+            #vec_id_ident: #vec_id_ident
+                .into_iter()
+                .collect::<Option<::enums_arena_defines::alloc::vec::Vec<_>>>()
+                .ok_or_else(|| <HIDE_D::Error as ::enums_arena_defines::serde::de::Error>::custom(concat!("unreferenced value of ", stringify!(#ident))))?
+        });
+    }
+
+    quote! {
+        const _: () = {
+            /// Names of the variants, a serialized arena stores the kind of a value
+            /// as the position of its variant in this list.
+            const __VARIANTS: &[&str] = &[#(#variant_names),*];
+
+            #[derive(::enums_arena_defines::serde::Serialize)]
+            #[serde(crate = "::enums_arena_defines::serde", rename = #arena_name)]
+            struct __EnumsArenaRef #ref_generics {
+                g: &'__enums_arena_ser HIDE_G,
+                generations: &'__enums_arena_ser [(usize, HIDE_G)],
+                kinds: &'__enums_arena_ser [#repr_ty],
+                offsets: &'__enums_arena_ser [HIDE_I],
+                schema: &'__enums_arena_ser [&'__enums_arena_ser str],
+                #(#ref_fields),*
+            }
+
+            #[derive(::enums_arena_defines::serde::Deserialize)]
+            #[serde(crate = "::enums_arena_defines::serde", rename = #arena_name)]
+            struct __EnumsArenaOwned #new_generics {
+                g: HIDE_G,
+                generations: ::enums_arena_defines::alloc::vec::Vec<(usize, HIDE_G)>,
+                kinds: ::enums_arena_defines::alloc::vec::Vec<#repr_ty>,
+                offsets: ::enums_arena_defines::alloc::vec::Vec<HIDE_I>,
+                schema: ::enums_arena_defines::alloc::vec::Vec<::enums_arena_defines::alloc::string::String>,
+                #(#owned_fields),*
+            }
+
+            impl #new_generics ::enums_arena_defines::serde::Serialize for #arena_name_ident #new_generics
+            where HIDE_I: ::enums_arena_defines::Index + ::enums_arena_defines::serde::Serialize,
+                HIDE_G: ::enums_arena_defines::Generation + ::enums_arena_defines::serde::Serialize,
+                #(#user_types: ::enums_arena_defines::serde::Serialize),*
+            {
+                fn serialize<HIDE_S: ::enums_arena_defines::serde::Serializer>(&self, serializer: HIDE_S) -> ::core::result::Result<HIDE_S::Ok, HIDE_S::Error> {
+                    let kinds: ::enums_arena_defines::alloc::vec::Vec<#repr_ty> = self.enums_vec_id_kind_of.iter().map(|ty| *ty as #repr_ty).collect();
+                    let arena = __EnumsArenaRef {
+                        g: &self.g,
+                        generations: self.enums_vec_id_g_of.runs(),
+                        kinds: &kinds,
+                        offsets: &self.enums_vec_id_offset_of,
+                        schema: __VARIANTS,
+                        #(#ref_take),*
+                    };
+                    ::enums_arena_defines::serde::Serialize::serialize(&arena, serializer)
+                }
+            }
+
+            impl<'__enums_arena_de, #params> ::enums_arena_defines::serde::Deserialize<'__enums_arena_de> for #arena_name_ident #new_generics
+            where HIDE_I: ::enums_arena_defines::Index + ::enums_arena_defines::serde::Deserialize<'__enums_arena_de>,
+                HIDE_G: ::enums_arena_defines::Generation + ::enums_arena_defines::serde::Deserialize<'__enums_arena_de>,
+                #(#user_types: ::enums_arena_defines::serde::Deserialize<'__enums_arena_de>),*
+            {
+                fn deserialize<HIDE_D: ::enums_arena_defines::serde::Deserializer<'__enums_arena_de>>(deserializer: HIDE_D) -> ::core::result::Result<Self, HIDE_D::Error> {
+                    Self::load(deserializer, &mut |removed| {
+                        Err(<HIDE_D::Error as ::enums_arena_defines::serde::de::Error>::unknown_variant(removed.variant, __VARIANTS))
+                    })
+                }
+            }
+
+            impl #new_generics #arena_name_ident #new_generics
+            where HIDE_I: ::enums_arena_defines::Index,
+                HIDE_G: ::enums_arena_defines::Generation,
+            {
+                /// Like `Deserialize`, but values of removed variants are passed to `on_removed`
+                /// and left out of the arena.
+                ///
+                /// Variants are matched by name, variants added since the arena was serialized
+                /// start empty. The values after the first removed one move to a new generation,
+                /// so their old ids find nothing.
+                #[allow(unused)]
+                pub fn deserialize_with<'__enums_arena_de, HIDE_D: ::enums_arena_defines::serde::Deserializer<'__enums_arena_de>>(
+                    deserializer: HIDE_D,
+                    mut on_removed: impl FnMut(::enums_arena_defines::RemovedKind<'_>),
+                ) -> ::core::result::Result<Self, HIDE_D::Error>
+                where HIDE_I: ::enums_arena_defines::serde::Deserialize<'__enums_arena_de>,
+                    HIDE_G: ::enums_arena_defines::serde::Deserialize<'__enums_arena_de>,
+                    #(#user_types: ::enums_arena_defines::serde::Deserialize<'__enums_arena_de>),*
+                {
+                    Self::load(deserializer, &mut |removed| {
+                        on_removed(removed);
+                        Ok(())
+                    })
+                }
+
+                #[allow(unreachable_code)]
+                fn load<'__enums_arena_de, HIDE_D: ::enums_arena_defines::serde::Deserializer<'__enums_arena_de>>(
+                    deserializer: HIDE_D,
+                    on_removed: &mut dyn FnMut(::enums_arena_defines::RemovedKind<'_>) -> ::core::result::Result<(), HIDE_D::Error>,
+                ) -> ::core::result::Result<Self, HIDE_D::Error>
+                where HIDE_I: ::enums_arena_defines::serde::Deserialize<'__enums_arena_de>,
+                    HIDE_G: ::enums_arena_defines::serde::Deserialize<'__enums_arena_de>,
+                    #(#user_types: ::enums_arena_defines::serde::Deserialize<'__enums_arena_de>),*
+                {
+                    let __EnumsArenaOwned {
+                        mut g,
+                        generations,
+                        kinds: tags,
+                        offsets: stored_offsets,
+                        schema,
+                        #(#field_names),*
+                    } = <__EnumsArenaOwned #new_generics as ::enums_arena_defines::serde::Deserialize>::deserialize(deserializer)?;
+                    if tags.len() != stored_offsets.len() {
+                        return Err(<HIDE_D::Error as ::enums_arena_defines::serde::de::Error>::custom("length of kinds and offsets mismatch"));
+                    }
+
+                    let mut generations = ::enums_arena_defines::Generations::from_runs(generations)
+                        .ok_or_else(|| <HIDE_D::Error as ::enums_arena_defines::serde::de::Error>::custom("generations out of order"))?;
+                    // The current kind of each stored variant, `None` if it is removed.
+                    let kind_map: ::enums_arena_defines::alloc::vec::Vec<Option<#enum_name_ident>> =
+                        schema.iter().map(|name| name.parse().ok()).collect();
+
+                    #(#id_of_defines)*
+                    let mut kinds = ::enums_arena_defines::alloc::vec::Vec::with_capacity(tags.len());
+                    let mut offsets = ::enums_arena_defines::alloc::vec::Vec::with_capacity(tags.len());
+                    for (index, (tag, real_index)) in tags.iter().zip(stored_offsets).enumerate() {
+                        let tag = *tag as usize;
+                        let ty = match kind_map.get(tag) {
+                            Some(Some(ty)) => *ty,
+                            Some(None) => {
+                                if offsets.len() == index {
+                                    generations.truncate(index, g);
+                                    g.add();
+                                }
+                                on_removed(::enums_arena_defines::RemovedKind { index, variant: &schema[tag] })?;
+                                continue;
+                            }
+                            None => return Err(<HIDE_D::Error as ::enums_arena_defines::serde::de::Error>::custom("invalid kind")),
+                        };
+                        match ty {
+                            #(#id_of_match_body),*
+                        }
+                        kinds.push(ty);
+                        offsets.push(real_index);
+                    }
+
+                    Ok(Self {
+                        g,
+                        enums_vec_id_offset_of: offsets,
+                        enums_vec_id_kind_of: kinds,
+                        enums_vec_id_g_of: generations,
+                        #(#arena_fields),*
+                    })
+                }
+            }
+        };
+    }
+}
@@ -0,0 +1,316 @@
+use proc_macro::Span;
+use proc_macro2::Literal;
+use quote::{format_ident, quote};
+use syn::{GenericParam, Lifetime, LifetimeParam};
+
+use crate::id_arena::Context;
+
+/// Generate `write_snapshot` and `{Name}ArenaView`.
+///
+/// Sections of a snapshot are the generations, the ends of the generation runs, the kinds,
+/// the offsets, the schema and the values of each variant with a payload in declaration order.
+pub fn expand(ctx: &Context) -> proc_macro2::TokenStream {
+    let Context {
+        name,
+        vis,
+        generics,
+        new_generics,
+        id_ident,
+        enum_name_ident,
+        repr_ty,
+        arena_name_ident,
+        variants,
+        ..
+    } = ctx;
+    let view_ident = format_ident!("{}ArenaView", name);
+
+    let mut view_generics = new_generics.clone();
+    view_generics.params.insert(
+        0,
+        GenericParam::Lifetime(LifetimeParam::new(Lifetime::new(
+            "'__enums_arena_snap",
+            Span::call_site().into(),
+        ))),
+    );
+
+    let mut payload_bounds = Vec::new();
+    let mut sections = Vec::new();
+    let mut view_fields = Vec::new();
+    let mut view_take = Vec::new();
+    let mut arena_fields = Vec::new();
+    let mut len_match_body = Vec::new();
+    let mut kind_match_body = Vec::new();
+    let mut get_match_body = Vec::new();
+    let mut field_fn = Vec::new();
+    let mut schema = Vec::new();
+
+    for (i, variant) in variants.iter().enumerate() {
+        let ident = &variant.ident;
+        let tag = Literal::usize_unsuffixed(i);
+        kind_match_body.push(quote! {
+            #tag => Some(#enum_name_ident::#ident)
+        });
+        let variant_name = ident.to_string();
+        let type_name = variant.ty.as_ref().map(|ty| quote!(#ty).to_string()).unwrap_or_default();
+        schema.push(quote! {
+            (#variant_name, #type_name)
+        });
+        let Some(ty) = &variant.ty else {
+            len_match_body.push(quote! {
+                #enum_name_ident::#ident => 1
+            });
+            get_match_body.push(quote! {
+                #enum_name_ident::#ident => Some(#name::#ident)
+            });
+            continue;
+        };
+        let vec_ident = format_ident!("{}_vec", variant.ident_case);
+        let vec_id_ident = format_ident!("{}_vec_id_of", variant.ident_case);
+        let get_ident = format_ident!("get_{}", variant.ident_case);
+        let values_ident = format_ident!("{}_values", variant.ident_case);
+
+        payload_bounds.push(quote! {
+            #ty: ::enums_arena_defines::snapshot::Pod
+        });
+        sections.push(quote! {
+            ::enums_arena_defines::snapshot::Section::new(&self.#vec_ident)
+        });
+        view_fields.push(quote! {
+            #vec_ident: &'__enums_arena_snap [#ty]
+        });
+        view_take.push(quote! {
+            #vec_ident: schema.section(#tag)?
+        });
+        arena_fields.push(quote! {
+            #vec_ident: ::enums_arena_defines::alloc::vec::Vec::new(),
+            #vec_id_ident: ::enums_arena_defines::alloc::vec::Vec::new()
+        });
+        len_match_body.push(quote! {
+            #enum_name_ident::#ident => view.#vec_ident.len()
+        });
+        get_match_body.push(quote! {
+            #enum_name_ident::#ident => Some(#name::#ident(*self.#vec_ident.get(real_index)?))
+        });
+        field_fn.push(quote! {
+            #[allow(unused)]
+            pub fn #get_ident(&self, id: #id_ident<HIDE_I, HIDE_G>) -> Option<&'__enums_arena_snap #ty> {
+                let (ty, real_index) = self.slot(id)?;
+                if let #enum_name_ident::#ident = ty {
+                    return self.#vec_ident.get(real_index);
+                }
+                None
+            }
+
+            /// All values of the variant, in storage order.
+            #[allow(unused)]
+            pub fn #values_ident(&self) -> &'__enums_arena_snap [#ty] {
+                self.#vec_ident
+            }
+        });
+    }
+    let schema = quote! {
+        &[#(#schema),*]
+    };
+
+    let bounds = quote! {
+        HIDE_I: ::enums_arena_defines::Index + ::enums_arena_defines::snapshot::Pod,
+        HIDE_G: ::enums_arena_defines::Generation + ::enums_arena_defines::snapshot::Pod,
+        #(#payload_bounds),*
+    };
+
+    quote! {
+        impl #new_generics #arena_name_ident #new_generics
+        where #bounds
+        {
+            /// Write the arena in the snapshot format, read it back with
+            #[doc = concat!("[`", stringify!(#view_ident), "::from_bytes`].")]
+            #[allow(unused)]
+            pub fn write_snapshot<HIDE_W: ::enums_arena_defines::snapshot::SnapshotWrite>(&self, w: &mut HIDE_W) -> ::core::result::Result<(), HIDE_W::Error> {
+                let runs = self.enums_vec_id_g_of.runs();
+                let gs: ::enums_arena_defines::alloc::vec::Vec<HIDE_G> = runs.iter().map(|(_, g)| *g).chain([self.g]).collect();
+                let ends: ::enums_arena_defines::alloc::vec::Vec<HIDE_I> = runs.iter().map(|(end, _)| HIDE_I::from_usize(*end)).collect();
+                let schema = ::enums_arena_defines::snapshot::encode_schema(#schema);
+                let kinds: ::enums_arena_defines::alloc::vec::Vec<#repr_ty> = self.enums_vec_id_kind_of.iter().map(|ty| *ty as #repr_ty).collect();
+                ::enums_arena_defines::snapshot::write_sections(w, &[
+                    ::enums_arena_defines::snapshot::Section::new(&gs),
+                    ::enums_arena_defines::snapshot::Section::new(&ends),
+                    ::enums_arena_defines::snapshot::Section::new(&kinds),
+                    ::enums_arena_defines::snapshot::Section::new(&self.enums_vec_id_offset_of),
+                    ::enums_arena_defines::snapshot::Section::new(&schema),
+                    #(#sections),*
+                ])
+            }
+        }
+
+        /// Read only arena borrowing its values from a snapshot.
+        #[derive(Clone)]
+        #vis struct #view_ident #view_generics {
+            g: HIDE_G,
+            generations: ::enums_arena_defines::Generations<HIDE_G>,
+            /// Current kind of each stored kind tag.
+            kind_map: ::enums_arena_defines::alloc::vec::Vec<Option<#enum_name_ident>>,
+            kinds: &'__enums_arena_snap [#repr_ty],
+            offsets: &'__enums_arena_snap [HIDE_I],
+            #(#view_fields),*
+        }
+
+        impl #view_generics #view_ident #view_generics
+        where #bounds
+        {
+            /// Check the snapshot and view it in place.
+            ///
+            /// The bytes must be aligned to
+            /// [`SECTION_ALIGN`](::enums_arena_defines::snapshot::SECTION_ALIGN).
+            /// Variants are matched by name, a value of a variant the enum doesn't have
+            /// fails with [`::enums_arena_defines::snapshot::SnapshotError::UnknownVariant`](::enums_arena_defines::snapshot::SnapshotError::UnknownVariant).
+            #[allow(unused)]
+            pub fn from_bytes(bytes: &'__enums_arena_snap [u8]) -> ::core::result::Result<Self, ::enums_arena_defines::snapshot::SnapshotError> {
+                Self::load(bytes, &mut |removed| {
+                    Err(::enums_arena_defines::snapshot::SnapshotError::UnknownVariant(removed.variant.into()))
+                })
+            }
+
+            /// Like [`from_bytes`](Self::from_bytes), but values of removed variants are passed
+            /// to `on_removed` and left out of the view.
+            #[allow(unused)]
+            pub fn from_bytes_with(
+                bytes: &'__enums_arena_snap [u8],
+                mut on_removed: impl FnMut(::enums_arena_defines::snapshot::RemovedValue<'__enums_arena_snap>),
+            ) -> ::core::result::Result<Self, ::enums_arena_defines::snapshot::SnapshotError> {
+                Self::load(bytes, &mut |removed| {
+                    on_removed(removed);
+                    Ok(())
+                })
+            }
+
+            fn load(
+                bytes: &'__enums_arena_snap [u8],
+                on_removed: &mut dyn FnMut(::enums_arena_defines::snapshot::RemovedValue<'__enums_arena_snap>) -> ::core::result::Result<(), ::enums_arena_defines::snapshot::SnapshotError>,
+            ) -> ::core::result::Result<Self, ::enums_arena_defines::snapshot::SnapshotError> {
+                let reader = ::enums_arena_defines::snapshot::SnapshotReader::new(bytes)?;
+                let schema = ::enums_arena_defines::snapshot::SchemaMap::new(reader, #schema)?;
+                let (g, gs) = reader.section::<HIDE_G>(0)?.split_last().ok_or(::enums_arena_defines::snapshot::SnapshotError::LayoutMismatch)?;
+                let ends = reader.section::<HIDE_I>(1)?;
+                if ends.len() != gs.len() {
+                    return Err(::enums_arena_defines::snapshot::SnapshotError::LayoutMismatch);
+                }
+                let runs = ends.iter().map(|end| end.to_usize()).zip(gs.iter().copied()).collect();
+                let view = Self {
+                    g: *g,
+                    generations: ::enums_arena_defines::Generations::from_runs(runs).ok_or(::enums_arena_defines::snapshot::SnapshotError::LayoutMismatch)?,
+                    kind_map: (0..schema.len())
+                        .map(|tag| schema.current_of(tag).and_then(Self::kind_from_index))
+                        .collect(),
+                    kinds: reader.section(2)?,
+                    offsets: reader.section(3)?,
+                    #(#view_take),*
+                };
+                if view.kinds.len() != view.offsets.len() {
+                    return Err(::enums_arena_defines::snapshot::SnapshotError::LayoutMismatch);
+                }
+                for (index, (tag, real_index)) in view.kinds.iter().zip(view.offsets).enumerate() {
+                    let tag = *tag as usize;
+                    let real_index = real_index.to_usize();
+                    let Some(ty) = *view.kind_map.get(tag).ok_or(::enums_arena_defines::snapshot::SnapshotError::InvalidKind)? else {
+                        on_removed(schema.removed(index, tag, real_index)?)?;
+                        continue;
+                    };
+                    let len = match ty {
+                        #(#len_match_body),*
+                    };
+                    if real_index >= len {
+                        return Err(::enums_arena_defines::snapshot::SnapshotError::InvalidOffset);
+                    }
+                }
+                Ok(view)
+            }
+
+            fn kind_from_index(i: usize) -> Option<#enum_name_ident> {
+                match i {
+                    #(#kind_match_body,)*
+                    _ => None,
+                }
+            }
+
+            fn slot_at(&self, index: usize) -> Option<(#enum_name_ident, usize)> {
+                let tag = *self.kinds.get(index)? as usize;
+                let ty = (*self.kind_map.get(tag)?)?;
+                let real_index = self.offsets.get(index)?.to_usize();
+                Some((ty, real_index))
+            }
+
+            fn slot(&self, id: #id_ident<HIDE_I, HIDE_G>) -> Option<(#enum_name_ident, usize)> {
+                let (index, g) = <#arena_name_ident #new_generics>::id_parts(id);
+                let index = index.to_usize();
+                if g != self.generations.get(index, self.g) {
+                    return None;
+                }
+                self.slot_at(index)
+            }
+
+            #[allow(unused)]
+            pub fn len(&self) -> usize {
+                self.kinds.len()
+            }
+
+            #[allow(unused)]
+            pub fn is_empty(&self) -> bool {
+                self.kinds.is_empty()
+            }
+
+            #[allow(unused)]
+            pub fn kind_of(&self, id: #id_ident<HIDE_I, HIDE_G>) -> Option<#enum_name_ident> {
+                Some(self.slot(id)?.0)
+            }
+
+            fn value_at(&self, index: usize) -> Option<#name #generics> {
+                let (ty, real_index) = self.slot_at(index)?;
+                match ty {
+                    #(#get_match_body),*
+                }
+            }
+
+            /// Returns `None` for a value of a removed variant.
+            #[allow(unused)]
+            pub fn get(&self, id: #id_ident<HIDE_I, HIDE_G>) -> Option<#name #generics> {
+                let (index, g) = <#arena_name_ident #new_generics>::id_parts(id);
+                let index = index.to_usize();
+                if g != self.generations.get(index, self.g) {
+                    return None;
+                }
+                self.value_at(index)
+            }
+
+            /// Copy the values into an arena, ids of the snapshot stay valid.
+            ///
+            /// Values of removed variants are left out, the values after the first of them
+            /// move to a new generation so their old ids find nothing.
+            #[allow(unused)]
+            pub fn to_arena(&self) -> #arena_name_ident #new_generics {
+                let mut arena = #arena_name_ident {
+                    g: self.g,
+                    enums_vec_id_offset_of: ::enums_arena_defines::alloc::vec::Vec::with_capacity(self.len()),
+                    enums_vec_id_kind_of: ::enums_arena_defines::alloc::vec::Vec::with_capacity(self.len()),
+                    enums_vec_id_g_of: self.generations.clone(),
+                    #(#arena_fields),*
+                };
+                let mut shifted = false;
+                for index in 0..self.len() {
+                    match self.value_at(index) {
+                        Some(val) => {
+                            arena.alloc(val);
+                        }
+                        None if !shifted => {
+                            arena.next_generation();
+                            shifted = true;
+                        }
+                        None => {}
+                    }
+                }
+                arena
+            }
+
+            #(#field_fn)*
+        }
+    }
+}
@@ -0,0 +1,150 @@
+use quote::{format_ident, quote};
+
+use crate::id_arena::{storage_type, Context};
+
+/// Generate `{Name}StableArena`, an arena which allocates through a shared reference.
+///
+/// Values are pushed with `StableStorage::push_ptr` and never move, so the returned
+/// references stay valid until the arena is borrowed mutably.
+pub fn expand(ctx: &Context) -> proc_macro2::TokenStream {
+    let Context {
+        name,
+        vis,
+        generics,
+        new_generics,
+        user_bound,
+        id_ident,
+        enum_name_ident,
+        arena_name_ident,
+        variants,
+        options,
+        ..
+    } = ctx;
+    let stable_ident = format_ident!("{}StableArena", name);
+
+    let mut storage_bounds = Vec::new();
+    let mut alloc_match_body = Vec::new();
+    let mut field_fn = Vec::new();
+
+    for variant in variants {
+        let ident = &variant.ident;
+        let alloc_ident = format_ident!("alloc_{}", variant.ident_case);
+        let Some(ty) = &variant.ty else {
+            alloc_match_body.push(quote! {
+                #name::#ident => self.#alloc_ident()
+            });
+            field_fn.push(quote! {
+                #[allow(unused)]
+                pub fn #alloc_ident(&self) -> #id_ident<HIDE_I, HIDE_G> {
+                    self.arena.borrow_mut().#alloc_ident()
+                }
+            });
+            continue;
+        };
+        let storage_ty = storage_type(options.storage.as_ref(), ty);
+        let alloc_ref_ident = format_ident!("alloc_{}_ref", variant.ident_case);
+        let get_ident = format_ident!("get_{}", variant.ident_case);
+        let vec_ident = format_ident!("{}_vec", variant.ident_case);
+        let vec_id_ident = format_ident!("{}_vec_id_of", variant.ident_case);
+
+        storage_bounds.push(quote! {
+            #storage_ty: ::enums_arena_defines::StableStorage
+        });
+        alloc_match_body.push(quote! {
+            #name::#ident(val) => self.#alloc_ref_ident(val).0
+        });
+        field_fn.push(quote! {
+            #[allow(unused)]
+            pub fn #alloc_ref_ident(&self, val: #ty) -> (#id_ident<HIDE_I, HIDE_G>, &#ty) {
+                let mut arena = self.arena.borrow_mut();
+                let index = HIDE_I::from_usize(arena.enums_vec_id_offset_of.len());
+                let real_index = HIDE_I::from_usize(::enums_arena_defines::Storage::len(&arena.#vec_ident));
+                let val = ::enums_arena_defines::StableStorage::push_ptr(&mut arena.#vec_ident, val);
+                arena.#vec_id_ident.push(index);
+                arena.enums_vec_id_offset_of.push(real_index);
+                arena.enums_vec_id_kind_of.push(#enum_name_ident::#ident);
+                let id = arena.new_id(#enum_name_ident::#ident, index);
+                // SAFETY: stable storage never moves its values when pushing, and they
+                // are not removed until the arena is borrowed mutably.
+                (id, unsafe { &*val })
+            }
+
+            #[allow(unused)]
+            pub fn #get_ident(&self, id: #id_ident<HIDE_I, HIDE_G>) -> Option<&#ty> {
+                let val: *const #ty = self.arena.borrow().#get_ident(id)?;
+                // SAFETY: see the alloc above.
+                Some(unsafe { &*val })
+            }
+        });
+    }
+
+    quote! {
+        /// Arena which allocates through a shared reference, like `typed_arena`.
+        ///
+        /// Values never move, references returned by the allocations are valid
+        /// as long as the arena is borrowed.
+        #[derive(Default)]
+        #vis struct #stable_ident #new_generics {
+            arena: ::core::cell::RefCell<#arena_name_ident #new_generics>,
+        }
+
+        impl #new_generics #stable_ident #new_generics
+        where HIDE_I: ::enums_arena_defines::Index,
+            HIDE_G: ::enums_arena_defines::Generation,
+            #(#storage_bounds),*
+        {
+            #[allow(unused)]
+            pub fn new(arena: #arena_name_ident #new_generics) -> Self {
+                Self {
+                    arena: ::core::cell::RefCell::new(arena),
+                }
+            }
+
+            #[allow(unused)]
+            pub fn into_inner(self) -> #arena_name_ident #new_generics {
+                self.arena.into_inner()
+            }
+
+            /// The inner arena, all references into it are released.
+            #[allow(unused)]
+            pub fn get_mut(&mut self) -> &mut #arena_name_ident #new_generics {
+                self.arena.get_mut()
+            }
+
+            #[allow(unused)]
+            pub fn len(&self) -> usize {
+                self.arena.borrow().len()
+            }
+
+            #[allow(unused)]
+            pub fn is_empty(&self) -> bool {
+                self.arena.borrow().is_empty()
+            }
+
+            #[allow(unused)]
+            pub fn kind_of(&self, id: #id_ident<HIDE_I, HIDE_G>) -> Option<#enum_name_ident> {
+                self.arena.borrow().kind_of(id)
+            }
+
+            #[allow(unused)]
+            pub fn alloc(&self, val: #name #generics) -> #id_ident<HIDE_I, HIDE_G> {
+                match val {
+                    #(#alloc_match_body),*
+                }
+            }
+
+            #(#field_fn)*
+        }
+
+        impl #new_generics #stable_ident #new_generics
+        where HIDE_I: ::enums_arena_defines::Index,
+            HIDE_G: ::enums_arena_defines::Generation,
+            #user_bound
+        {
+            #[allow(unused)]
+            pub fn get(&self, id: #id_ident<HIDE_I, HIDE_G>) -> Option<#name #generics> {
+                self.arena.borrow().get(id)
+            }
+        }
+    }
+}
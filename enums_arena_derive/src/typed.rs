@@ -0,0 +1,169 @@
+use quote::{format_ident, quote};
+use syn::{GenericParam, Generics, Type};
+
+use crate::id_arena::{Context, Variant};
+
+/// Check that the payloads of `variants` can each have their own trait impls:
+/// no two of them share a type and none is a bare type parameter.
+///
+/// `option` and `what` name the option and the generated impls in the error.
+pub fn check_payloads<'v>(
+    generics: &Generics,
+    variants: impl Iterator<Item = &'v Variant>,
+    option: &str,
+    what: &str,
+) -> syn::Result<()> {
+    let mut seen: Vec<(String, &syn::Ident)> = Vec::new();
+    for variant in variants {
+        let ident = &variant.ident;
+        let Some(ty) = &variant.ty else {
+            continue;
+        };
+        if let Type::Path(path) = ty {
+            if let Some(param) = path.path.get_ident() {
+                if path.qself.is_none() && generics.type_params().any(|t| t.ident == *param) {
+                    return Err(syn::Error::new_spanned(
+                        ty,
+                        format!(
+                            "`{}` can't implement {} for the type parameter `{}` of variant `{}`",
+                            option, what, param, ident
+                        ),
+                    ));
+                }
+            }
+        }
+        let ty_str = quote!(#ty).to_string();
+        if let Some((_, first)) = seen.iter().find(|(seen_ty, _)| *seen_ty == ty_str) {
+            return Err(syn::Error::new_spanned(
+                ty,
+                format!(
+                    "`{}` needs a distinct payload type per variant, `{}` is the payload of both `{}` and `{}`",
+                    option, ty_str, first, ident
+                ),
+            ));
+        }
+        seen.push((ty_str, ident));
+    }
+    Ok(())
+}
+
+/// Implement `ArenaVariant` for the payload type of each variant, and generate
+/// `alloc_typed`, `get_as`, `get_as_mut` and `iter_of` of the arena.
+///
+/// Fails if two variants share a payload type, or a payload is a bare type parameter.
+pub fn expand(ctx: &Context) -> syn::Result<proc_macro2::TokenStream> {
+    let Context {
+        name,
+        generics,
+        new_generics,
+        id_ident,
+        enum_name_ident,
+        arena_name_ident,
+        variants,
+        ..
+    } = ctx;
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+    let (_, arena_ty_generics, _) = new_generics.split_for_impl();
+    // `HIDE_V::iter` captures the lifetimes of the user enum.
+    let captures = new_generics.params.iter().map(|param| match param {
+        GenericParam::Lifetime(param) => {
+            let lifetime = &param.lifetime;
+            quote!(#lifetime)
+        }
+        GenericParam::Type(param) => {
+            let ident = &param.ident;
+            quote!(#ident)
+        }
+        GenericParam::Const(param) => {
+            let ident = &param.ident;
+            quote!(#ident)
+        }
+    });
+
+    check_payloads(generics, variants.iter(), "typed", "`ArenaVariant`")?;
+
+    let mut variant_impls = Vec::new();
+
+    for variant in variants {
+        let ident = &variant.ident;
+        let Some(ty) = &variant.ty else {
+            continue;
+        };
+        let alloc_ident = format_ident!("alloc_{}", variant.ident_case);
+        let get_ident = format_ident!("get_{}", variant.ident_case);
+        let get_mut_ident = format_ident!("get_{}_mut", variant.ident_case);
+        let vec_ident = format_ident!("{}_vec", variant.ident_case);
+        let vec_id_ident = format_ident!("{}_vec_id_of", variant.ident_case);
+
+        variant_impls.push(quote! {
+            impl #impl_generics ::enums_arena_defines::ArenaVariant<#name #ty_generics> for #ty #where_clause {
+                const KIND: #enum_name_ident = #enum_name_ident::#ident;
+
+                fn alloc<HIDE_I: ::enums_arena_defines::Index, HIDE_G: ::enums_arena_defines::Generation>(
+                    arena: &mut #arena_name_ident #arena_ty_generics,
+                    val: Self,
+                ) -> #id_ident<HIDE_I, HIDE_G> {
+                    arena.#alloc_ident(val)
+                }
+
+                fn get<'__enums_arena_get, HIDE_I: ::enums_arena_defines::Index, HIDE_G: ::enums_arena_defines::Generation>(
+                    arena: &'__enums_arena_get #arena_name_ident #arena_ty_generics,
+                    id: #id_ident<HIDE_I, HIDE_G>,
+                ) -> Option<&'__enums_arena_get Self> {
+                    arena.#get_ident(id)
+                }
+
+                fn get_mut<'__enums_arena_get, HIDE_I: ::enums_arena_defines::Index, HIDE_G: ::enums_arena_defines::Generation>(
+                    arena: &'__enums_arena_get mut #arena_name_ident #arena_ty_generics,
+                    id: #id_ident<HIDE_I, HIDE_G>,
+                ) -> Option<&'__enums_arena_get mut Self> {
+                    arena.#get_mut_ident(id)
+                }
+
+                fn iter<'__enums_arena_iter, HIDE_I: ::enums_arena_defines::Index, HIDE_G: ::enums_arena_defines::Generation>(
+                    arena: &'__enums_arena_iter #arena_name_ident #arena_ty_generics,
+                ) -> impl Iterator<Item = (#id_ident<HIDE_I, HIDE_G>, &'__enums_arena_iter Self)>
+                where
+                    Self: '__enums_arena_iter,
+                {
+                    arena.#vec_id_ident.iter().enumerate().filter_map(move |(real_index, index)| {
+                        let val = ::enums_arena_defines::Storage::get(&arena.#vec_ident, real_index)?;
+                        Some((arena.new_id(#enum_name_ident::#ident, *index), val))
+                    })
+                }
+            }
+        });
+    }
+
+    Ok(quote! {
+        #(#variant_impls)*
+
+        impl #new_generics #arena_name_ident #new_generics
+        where HIDE_I: ::enums_arena_defines::Index,
+            HIDE_G: ::enums_arena_defines::Generation,
+        {
+            /// Allocate a payload into the variant of its type.
+            #[allow(unused)]
+            pub fn alloc_typed<HIDE_V: ::enums_arena_defines::ArenaVariant<#name #generics>>(&mut self, val: HIDE_V) -> #id_ident<HIDE_I, HIDE_G> {
+                HIDE_V::alloc(self, val)
+            }
+
+            /// Returns `None` if `id` is not a value of the variant of the payload type.
+            #[allow(unused)]
+            pub fn get_as<'__enums_arena_get, HIDE_V: ::enums_arena_defines::ArenaVariant<#name #generics> + '__enums_arena_get>(&'__enums_arena_get self, id: #id_ident<HIDE_I, HIDE_G>) -> Option<&'__enums_arena_get HIDE_V> {
+                HIDE_V::get(self, id)
+            }
+
+            #[allow(unused)]
+            pub fn get_as_mut<'__enums_arena_get, HIDE_V: ::enums_arena_defines::ArenaVariant<#name #generics> + '__enums_arena_get>(&'__enums_arena_get mut self, id: #id_ident<HIDE_I, HIDE_G>) -> Option<&'__enums_arena_get mut HIDE_V> {
+                HIDE_V::get_mut(self, id)
+            }
+
+            /// Payloads of the variant of the payload type, in storage order.
+            #[allow(unused)]
+            pub fn iter_of<'__enums_arena_iter, HIDE_V: ::enums_arena_defines::ArenaVariant<#name #generics> + '__enums_arena_iter>(&'__enums_arena_iter self) -> impl Iterator<Item = (#id_ident<HIDE_I, HIDE_G>, &'__enums_arena_iter HIDE_V)> + use<'__enums_arena_iter, #(#captures,)* HIDE_V> {
+                HIDE_V::iter(self)
+            }
+        }
+    })
+}
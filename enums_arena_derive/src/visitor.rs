@@ -0,0 +1,103 @@
+use quote::{format_ident, quote};
+
+use crate::id_arena::Context;
+
+/// Generate `{Name}Visitor` and `visit`/`visit_all` of the arena.
+pub fn expand(ctx: &Context) -> proc_macro2::TokenStream {
+    let Context {
+        name,
+        vis,
+        new_generics,
+        id_ident,
+        enum_name_ident,
+        arena_name_ident,
+        variants,
+        ..
+    } = ctx;
+    let visitor_ident = format_ident!("{}Visitor", name);
+
+    let mut visit_fn = Vec::new();
+    let mut visit_match_body = Vec::new();
+    let mut variant_major = Vec::new();
+
+    for variant in variants {
+        let ident = &variant.ident;
+        let visit_ident = format_ident!("visit_{}", variant.ident_case);
+        let Some(ty) = &variant.ty else {
+            visit_fn.push(quote! {
+                fn #visit_ident(&mut self, id: #id_ident<HIDE_I, HIDE_G>) {}
+            });
+            visit_match_body.push(quote! {
+                #enum_name_ident::#ident => v.#visit_ident(id)
+            });
+            variant_major.push(quote! {
+                for (index, ty) in self.enums_vec_id_kind_of.iter().enumerate() {
+                    if *ty == #enum_name_ident::#ident {
+                        v.#visit_ident(self.new_id(*ty, HIDE_I::from_usize(index)));
+                    }
+                }
+            });
+            continue;
+        };
+        let vec_ident = format_ident!("{}_vec", variant.ident_case);
+        let vec_id_ident = format_ident!("{}_vec_id_of", variant.ident_case);
+        visit_fn.push(quote! {
+            fn #visit_ident(&mut self, id: #id_ident<HIDE_I, HIDE_G>, val: &#ty) {}
+        });
+        visit_match_body.push(quote! {
+            #enum_name_ident::#ident => {
+                if let Some(val) = ::enums_arena_defines::Storage::get(&self.#vec_ident, real_index) {
+                    v.#visit_ident(id, val);
+                }
+            }
+        });
+        variant_major.push(quote! {
+            for (real_index, index) in self.#vec_id_ident.iter().enumerate() {
+                if let Some(val) = ::enums_arena_defines::Storage::get(&self.#vec_ident, real_index) {
+                    v.#visit_ident(self.new_id(#enum_name_ident::#ident, *index), val);
+                }
+            }
+        });
+    }
+
+    quote! {
+        /// Visitor of the values of an arena, every method does nothing by default.
+        #[allow(unused, clippy::ptr_arg)]
+        #vis trait #visitor_ident #new_generics {
+            #(#visit_fn)*
+        }
+
+        impl #new_generics #arena_name_ident #new_generics
+        where HIDE_I: ::enums_arena_defines::Index,
+            HIDE_G: ::enums_arena_defines::Generation,
+        {
+            /// Call the method of the variant of `id`, returns `false` if the id is not found.
+            #[allow(unused)]
+            pub fn visit<HIDE_V: #visitor_ident #new_generics + ?Sized>(&self, id: #id_ident<HIDE_I, HIDE_G>, v: &mut HIDE_V) -> bool {
+                let Some((ty, real_index)) = self.slot(id) else {
+                    return false;
+                };
+                match ty {
+                    #(#visit_match_body),*
+                }
+                true
+            }
+
+            #[allow(unused)]
+            pub fn visit_all<HIDE_V: #visitor_ident #new_generics + ?Sized>(&self, v: &mut HIDE_V, order: ::enums_arena_defines::VisitOrder) {
+                match order {
+                    ::enums_arena_defines::VisitOrder::Allocation => {
+                        for index in 0..self.len() {
+                            if let Some(id) = self.id_at(HIDE_I::from_usize(index)) {
+                                self.visit(id, v);
+                            }
+                        }
+                    }
+                    ::enums_arena_defines::VisitOrder::VariantMajor => {
+                        #(#variant_major)*
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,52 @@
+//! Arenas derived in a `#![no_std]` crate, the CI builds it for a target without `std`.
+#![cfg_attr(not(test), no_std)]
+
+extern crate alloc;
+
+use alloc::string::String;
+use enums_arena::EnumsIdArena;
+
+#[derive(EnumsIdArena, Clone, PartialEq, Debug)]
+#[enums_arena(journal, snapshot, ring, typed)]
+pub enum Sample {
+    Value(u64),
+    Pair([u32; 2]),
+    Gap,
+}
+
+#[derive(EnumsIdArena, Clone, PartialEq, Debug)]
+#[enums_arena(stable)]
+pub enum Node {
+    Name(String),
+    Weight(u32),
+    Leaf,
+}
+
+/// Use every generated arena once, so their methods are instantiated too.
+pub fn exercise() -> Option<u64> {
+    let mut journal = SampleJournal::<u32, u8>::default();
+    let id = journal.alloc_value(1);
+    journal.modify_value(id, |val| *val += 1)?;
+    journal.undo()?;
+
+    let arena = journal.into_inner();
+    let mut bytes = alloc::vec::Vec::new();
+    arena.write_snapshot(&mut bytes).ok()?;
+    let bytes = enums_arena_defines::snapshot::AlignedBytes::new(&bytes);
+    let view = SampleArenaView::<u32, u8>::from_bytes(&bytes).ok()?;
+    let value = *view.get_value(id)?;
+
+    let mut ring = SampleRingArena::<u16>::new(4);
+    ring.alloc_pair([1, 2]);
+    ring.alloc_gap();
+
+    let mut typed = SampleIdArena::<u32, u8>::default();
+    typed.alloc_typed(2u64);
+
+    let stable = NodeStableArena::<u32, u8>::default();
+    let (_, name) = stable.alloc_name_ref(String::from("a"));
+    stable.alloc_weight_ref(1);
+    stable.alloc(Node::Leaf);
+
+    Some(value + name.len() as u64 + ring.len() as u64 + typed.iter_of::<u64>().count() as u64)
+}